@@ -0,0 +1,1037 @@
+//! The operations of the VinylDNS api, described once and shared by the blocking `Client` and
+//! the `AsyncClient`.
+//!
+//! Each operation builds an `ApiRequest`, which a client signs with `ApiRequest::sign`, sends
+//! however it likes, and then turns back into a value with `decode`.
+
+use crate::api_types::*;
+use crate::aws_sign;
+use crate::client::ClientError::{self, *};
+use crate::client::{ErrorResponse, WaitOptions};
+use crate::credentials::Credentials;
+use crate::names;
+use log::*;
+use reqwest::header;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+/// Region requests are signed for unless configured otherwise
+pub const DEFAULT_REGION: &str = "us-east-1";
+/// Service name requests are signed for unless configured otherwise
+pub const DEFAULT_SERVICE: &str = "s3";
+
+/// A request to the api, before it is signed
+#[derive(Debug, Clone)]
+pub struct ApiRequest {
+    pub method: reqwest::Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // like reqwest's builder, failing to serialize a body is reported when the request is sent
+    error: Option<String>,
+}
+
+impl ApiRequest {
+    pub fn new(method: reqwest::Method, path: impl Into<String>) -> Self {
+        ApiRequest {
+            method,
+            path: path.into(),
+            query: vec![],
+            body: vec![],
+            error: None,
+        }
+    }
+
+    pub fn get(path: impl Into<String>) -> Self {
+        ApiRequest::new(reqwest::Method::GET, path)
+    }
+
+    pub fn post(path: impl Into<String>) -> Self {
+        ApiRequest::new(reqwest::Method::POST, path)
+    }
+
+    pub fn put(path: impl Into<String>) -> Self {
+        ApiRequest::new(reqwest::Method::PUT, path)
+    }
+
+    pub fn delete(path: impl Into<String>) -> Self {
+        ApiRequest::new(reqwest::Method::DELETE, path)
+    }
+
+    /// Set a query parameter, replacing any previous value
+    pub fn param(mut self, key: &str, value: impl ToString) -> Self {
+        self.query.retain(|(k, _)| k != key);
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn json<B: Serialize>(mut self, body: &B) -> Self {
+        match serde_json::to_vec(body) {
+            Ok(body) => self.body = body,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self
+    }
+
+    /// Build the request against `host`, signed with `credentials` for `region` and `service` at
+    /// the current time
+    pub fn sign(
+        &self,
+        host: &str,
+        credentials: &Credentials,
+        region: &str,
+        service: &str,
+    ) -> Result<SignedRequest> {
+        if let Some(e) = &self.error {
+            return Err(Serializing(e.clone()).into());
+        }
+        let dt = aws_sign::Utc::now();
+
+        trace!(
+            "{} {} {:?}\n{}",
+            self.method,
+            self.path,
+            self.query,
+            std::str::from_utf8(&self.body).unwrap_or_default()
+        );
+
+        let mut url: reqwest::Url = format!("{}{}", host, self.path).parse()?;
+        if !self.query.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.query);
+        }
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/json".parse()?);
+        aws_sign::prepare_request(&url, &mut headers, dt, &self.body);
+        let auth_val = aws_sign::auth_header(
+            &self.method,
+            &url,
+            &headers,
+            &self.body,
+            dt,
+            region,
+            service,
+            &credentials.access_key,
+            credentials.secret_key.expose(),
+        );
+        headers.insert(header::AUTHORIZATION, auth_val.parse()?);
+
+        trace!("{:?}", headers);
+        Ok(SignedRequest {
+            method: self.method.clone(),
+            url,
+            headers,
+            body: self.body.clone(),
+        })
+    }
+}
+
+/// A request with everything needed to send it, including its `Authorization` header
+#[derive(Debug, Clone)]
+pub struct SignedRequest {
+    pub method: reqwest::Method,
+    pub url: reqwest::Url,
+    pub headers: header::HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// Decode the response to `request`, turning non-2xx statuses into the matching `ClientError`
+pub fn decode<R: DeserializeOwned>(request: &ApiRequest, status: u16, body: String) -> Result<R> {
+    if !(200..300).contains(&status) {
+        return Err(ClientError::from_response(ErrorResponse::new(
+            status,
+            request.method.as_str(),
+            &request.path,
+            body,
+        ))
+        .into());
+    }
+    let res = serde_json::from_str(&body).map_err(|e| Deserializing(e, body))?;
+    Ok(res)
+}
+
+/// The operations of the VinylDNS api.
+///
+/// Every operation is built on `execute`, so the blocking `Client` (where operations return a
+/// `Result`) and the `AsyncClient` (where they return a future of one) can't drift apart.
+pub trait VinylDns {
+    /// What operations return: `Result<T>`, or a future of it
+    type Output<'a, T: 'a>
+    where
+        Self: 'a;
+
+    /// Sign and send a request, decoding its response
+    fn execute<'a, R: DeserializeOwned + Send + 'a>(
+        &'a self,
+        request: ApiRequest,
+    ) -> Self::Output<'a, R>;
+
+    /// Transform the result of an operation
+    fn then<'a, T: Send + 'a, U: Send + 'a>(
+        output: Self::Output<'a, T>,
+        f: impl FnOnce(Result<T>) -> Result<U> + Send + 'a,
+    ) -> Self::Output<'a, U>
+    where
+        Self: 'a;
+
+    /// Request every page of a listing, collecting all of their items
+    fn all_pages<'a, P: Page + 'a>(&'a self, request: ApiRequest)
+        -> Self::Output<'a, Vec<P::Item>>;
+
+    fn zones(&self) -> Self::Output<'_, Vec<Zone>> {
+        self.all_pages::<Zones>(ListOptions::new().zones())
+    }
+
+    fn zones_page(&self, options: &ListOptions) -> Self::Output<'_, Zones> {
+        self.execute(options.zones())
+    }
+
+    fn zone(&self, id: &str) -> Self::Output<'_, Zone> {
+        Self::then(
+            self.execute(ApiRequest::get(format!("/zones/{}", id))),
+            |zone: Result<ZoneResponse>| Ok(zone?.zone),
+        )
+    }
+
+    /// The zone named `name`, with or without its trailing dot
+    fn zone_by_name(&self, name: &str) -> Self::Output<'_, Zone> {
+        Self::then(
+            self.execute(ApiRequest::get(format!(
+                "/zones/name/{}",
+                names::absolute(name)
+            ))),
+            |zone: Result<ZoneResponse>| Ok(zone?.zone),
+        )
+    }
+
+    fn zone_create(&self, zone: &Zone) -> Self::Output<'_, ZoneUpdateResponse> {
+        self.execute(ApiRequest::post("/zones").json(zone))
+    }
+
+    fn zone_update(&self, id: &str, zone: &Zone) -> Self::Output<'_, ZoneUpdateResponse> {
+        self.execute(ApiRequest::put(format!("/zones/{}", id)).json(zone))
+    }
+
+    fn zone_delete(&self, id: &str) -> Self::Output<'_, ZoneUpdateResponse> {
+        self.execute(ApiRequest::delete(format!("/zones/{}", id)))
+    }
+
+    /// Start syncing the zone's record sets with its dns backend
+    fn zone_sync(&self, id: &str) -> Self::Output<'_, ZoneChange> {
+        self.execute(ApiRequest::post(format!("/zones/{}/sync", id)))
+    }
+
+    /// Add a rule to the zone's ACL
+    fn acl_rule_add(&self, zone_id: &str, rule: &ACLRule) -> Self::Output<'_, ZoneChange> {
+        self.execute(ApiRequest::put(format!("/zones/{}/acl/rules", zone_id)).json(rule))
+    }
+
+    /// Remove a rule, matched on all of its fields, from the zone's ACL
+    fn acl_rule_delete(&self, zone_id: &str, rule: &ACLRule) -> Self::Output<'_, ZoneChange> {
+        self.execute(ApiRequest::delete(format!("/zones/{}/acl/rules", zone_id)).json(rule))
+    }
+
+    fn zone_changes(&self, id: &str) -> Self::Output<'_, Vec<ZoneChange>> {
+        self.all_pages::<ZoneChanges>(ListOptions::new().zone_changes(id))
+    }
+
+    fn zone_changes_page(&self, id: &str, options: &ListOptions) -> Self::Output<'_, ZoneChanges> {
+        self.execute(options.zone_changes(id))
+    }
+
+    fn record_sets(&self, zone_id: &str) -> Self::Output<'_, Vec<RecordSet>> {
+        self.all_pages::<RecordSetsResponse>(ListOptions::new().record_sets(zone_id))
+    }
+
+    fn record_sets_page(
+        &self,
+        zone_id: &str,
+        options: &ListOptions,
+    ) -> Self::Output<'_, RecordSetsResponse> {
+        self.execute(options.record_sets(zone_id))
+    }
+
+    /// Every record set, in any zone, matching the options' name filter and record set filters
+    fn record_sets_search(&self, options: &ListOptions) -> Self::Output<'_, Vec<RecordSetInfo>> {
+        self.all_pages::<RecordSetSearchResponse>(options.record_sets_search())
+    }
+
+    fn record_sets_search_page(
+        &self,
+        options: &ListOptions,
+    ) -> Self::Output<'_, RecordSetSearchResponse> {
+        self.execute(options.record_sets_search())
+    }
+
+    /// Record sets of a zone matching the options' name filter and record set filters
+    fn record_sets_filtered(
+        &self,
+        zone_id: &str,
+        options: &ListOptions,
+    ) -> Self::Output<'_, Vec<RecordSet>> {
+        self.all_pages::<RecordSetsResponse>(options.record_sets(zone_id))
+    }
+
+    /// The record set of `zone` with this name and type, ignoring case.
+    ///
+    /// `name` is relative to the zone, like the names VinylDNS gives record sets, or fully
+    /// qualified with a trailing dot. `@` or the zone's own name is its apex.
+    fn record_set_by_name_and_type(
+        &self,
+        zone: &Zone,
+        name: &str,
+        record_type: RecordType,
+    ) -> Self::Output<'_, Option<RecordSet>> {
+        let origin = names::absolute(&zone.name);
+        let name = names::relative_name(name, &origin);
+        let mut options = ListOptions::new().record_type_filter(record_type.clone());
+        // the apex may be named `@` or after the zone, so it can't be filtered by name
+        if name != "@" {
+            options = options.name_filter(&name);
+        }
+        Self::then(
+            self.record_sets_filtered(&zone.id, &options),
+            move |record_sets| {
+                Ok(record_sets?.into_iter().find(|rs| {
+                    rs.record_set_type == record_type
+                        && names::relative_name(&rs.name, &origin).eq_ignore_ascii_case(&name)
+                }))
+            },
+        )
+    }
+
+    fn record_set(&self, zone_id: &str, id: &str) -> Self::Output<'_, RecordSet> {
+        self.execute(ApiRequest::get(format!(
+            "/zones/{}/recordsets/{}",
+            zone_id, id
+        )))
+    }
+
+    fn record_set_create(
+        &self,
+        zone_id: &str,
+        rs: &RecordSet,
+    ) -> Self::Output<'_, RecordSetUpdateResponse> {
+        self.execute(ApiRequest::post(format!("/zones/{}/recordsets", zone_id)).json(rs))
+    }
+
+    fn record_set_update(
+        &self,
+        zone_id: &str,
+        id: &str,
+        rs: &RecordSet,
+    ) -> Self::Output<'_, RecordSetUpdateResponse> {
+        self.execute(ApiRequest::put(format!("/zones/{}/recordsets/{}", zone_id, id)).json(rs))
+    }
+
+    fn record_set_delete(
+        &self,
+        zone_id: &str,
+        id: &str,
+    ) -> Self::Output<'_, RecordSetUpdateResponse> {
+        self.execute(ApiRequest::delete(format!(
+            "/zones/{}/recordsets/{}",
+            zone_id, id
+        )))
+    }
+
+    fn record_set_changes(&self, zone_id: &str) -> Self::Output<'_, Vec<RecordSetChange>> {
+        self.all_pages::<RecordSetChanges>(ListOptions::new().record_set_changes(zone_id))
+    }
+
+    fn record_set_changes_page(
+        &self,
+        zone_id: &str,
+        options: &ListOptions,
+    ) -> Self::Output<'_, RecordSetChanges> {
+        self.execute(options.record_set_changes(zone_id))
+    }
+
+    fn record_set_change(
+        &self,
+        zone_id: &str,
+        record_set_id: &str,
+        change_id: &str,
+    ) -> Self::Output<'_, RecordSetChange> {
+        self.execute(ApiRequest::get(format!(
+            "/zones/{}/recordsets/{}/changes/{}",
+            zone_id, record_set_id, change_id
+        )))
+    }
+
+    fn groups(&self) -> Self::Output<'_, Vec<Group>> {
+        self.all_pages::<Groups>(ListOptions::new().groups())
+    }
+
+    fn groups_page(&self, options: &ListOptions) -> Self::Output<'_, Groups> {
+        self.execute(options.groups())
+    }
+
+    fn group_create(&self, group: &Group) -> Self::Output<'_, Group> {
+        self.execute(ApiRequest::post("/groups").json(group))
+    }
+
+    fn group(&self, group_id: &str) -> Self::Output<'_, Group> {
+        self.execute(ApiRequest::get(format!("/groups/{}", group_id)))
+    }
+
+    fn group_delete(&self, group_id: &str) -> Self::Output<'_, Group> {
+        self.execute(ApiRequest::delete(format!("/groups/{}", group_id)))
+    }
+
+    fn group_update(&self, group_id: &str, group: &Group) -> Self::Output<'_, Group> {
+        self.execute(ApiRequest::put(format!("/groups/{}", group_id)).json(group))
+    }
+
+    /// The user with the id or user name `user`
+    fn user(&self, user: &str) -> Self::Output<'_, User> {
+        self.execute(ApiRequest::get(format!("/users/{}", user)))
+    }
+
+    fn group_admins(&self, group_id: &str) -> Self::Output<'_, Vec<User>> {
+        Self::then(
+            self.execute(ApiRequest::get(format!("/groups/{}/admins", group_id))),
+            |admins: Result<GroupAdmins>| Ok(admins?.admins),
+        )
+    }
+
+    fn group_members(&self, group_id: &str) -> Self::Output<'_, Vec<User>> {
+        Self::then(
+            self.execute(ApiRequest::get(format!("/groups/{}/members", group_id))),
+            |members: Result<GroupMembers>| Ok(members?.members),
+        )
+    }
+
+    fn group_activity(&self, group_id: &str) -> Self::Output<'_, GroupChanges> {
+        self.execute(ApiRequest::get(format!("/groups/{}/activity", group_id)))
+    }
+
+    fn batch_changes(&self) -> Self::Output<'_, Vec<BatchRecordChangeSummary>> {
+        self.all_pages::<BatchRecordChanges>(ListOptions::new().batch_changes(None))
+    }
+
+    /// List batch changes, optionally only those with the given approval status
+    fn batch_changes_page(
+        &self,
+        approval_status: Option<BatchChangeApprovalStatus>,
+        options: &ListOptions,
+    ) -> Self::Output<'_, BatchRecordChanges> {
+        self.execute(options.batch_changes(approval_status))
+    }
+
+    fn batch_change(&self, id: &str) -> Self::Output<'_, BatchRecordChange> {
+        self.execute(ApiRequest::get(format!("/zones/batchrecordchanges/{}", id)))
+    }
+
+    /// Submit a batch change, which is applied atomically across zones.
+    ///
+    /// If `allow_manual_review` is set, changes which would otherwise fail validation may be
+    /// held for review by a support user instead of being rejected. Changes which do fail
+    /// validation are returned as a `ClientError::BatchValidation`.
+    fn batch_change_create(
+        &self,
+        batch: &BatchRecordChangeInput,
+        allow_manual_review: bool,
+    ) -> Self::Output<'_, BatchRecordChange> {
+        Self::then(
+            self.execute(
+                ApiRequest::post("/zones/batchrecordchanges")
+                    .param("allowManualReview", allow_manual_review)
+                    .json(batch),
+            ),
+            |batch| batch.map_err(batch_validation_error),
+        )
+    }
+
+    fn batch_change_cancel(&self, id: &str) -> Self::Output<'_, BatchRecordChange> {
+        self.execute(ApiRequest::post(format!(
+            "/zones/batchrecordchanges/{}/cancel",
+            id
+        )))
+    }
+
+    fn batch_change_approve(
+        &self,
+        id: &str,
+        review_comment: Option<&str>,
+    ) -> Self::Output<'_, BatchRecordChange> {
+        self.batch_change_review(id, "approve", review_comment)
+    }
+
+    fn batch_change_reject(
+        &self,
+        id: &str,
+        review_comment: Option<&str>,
+    ) -> Self::Output<'_, BatchRecordChange> {
+        self.batch_change_review(id, "reject", review_comment)
+    }
+
+    #[doc(hidden)]
+    fn batch_change_review(
+        &self,
+        id: &str,
+        action: &str,
+        review_comment: Option<&str>,
+    ) -> Self::Output<'_, BatchRecordChange> {
+        let review = BatchRecordChangeReview {
+            review_comment: review_comment.map(str::to_string),
+        };
+        Self::then(
+            self.execute(
+                ApiRequest::post(format!("/zones/batchrecordchanges/{}/{}", id, action))
+                    .json(&review),
+            ),
+            |batch| batch.map_err(batch_validation_error),
+        )
+    }
+}
+
+/// A rejected batch change responds with the list of its changes, annotated with the errors
+/// for each one
+fn batch_validation_error(e: failure::Error) -> failure::Error {
+    match e.downcast::<ClientError>() {
+        Ok(BadRequest(response)) => {
+            match serde_json::from_str::<Vec<RecordChangeError>>(&response.body) {
+                Ok(errors) => {
+                    let summary = errors
+                        .iter()
+                        .filter(|change| !change.errors.is_empty())
+                        .map(|change| {
+                            format!(
+                                "{} {} {}: {}",
+                                change.change.change_type,
+                                change.change.typ,
+                                change.change.input_name,
+                                change.errors.join("; ")
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    BatchValidation(summary, errors).into()
+                }
+                Err(_) => BadRequest(response).into(),
+            }
+        }
+        Ok(e) => e.into(),
+        Err(e) => e,
+    }
+}
+
+/// Filtering and paging options for listings.
+///
+/// Filters which a listing doesn't support are ignored.
+#[derive(Debug, Default, Clone)]
+pub struct ListOptions {
+    name_filter: Option<String>,
+    max_items: Option<u32>,
+    start_from: Option<String>,
+    ignore_access: bool,
+    record_type_filter: Vec<RecordType>,
+    record_owner_group_filter: Option<String>,
+    name_sort: Option<NameSort>,
+}
+
+impl ListOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Only list items whose name contains this string
+    pub fn name_filter(mut self, name_filter: &str) -> Self {
+        self.name_filter = Some(name_filter.to_string());
+        self
+    }
+
+    /// Number of items requested per page
+    pub fn max_items(mut self, max_items: u32) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Start listing from a `nextId` returned by a previous listing
+    pub fn start_from(mut self, start_from: &str) -> Self {
+        self.start_from = Some(start_from.to_string());
+        self
+    }
+
+    /// List all zones, groups or batch changes, not just those the current user has access to
+    /// (requires a support user)
+    pub fn ignore_access(mut self, ignore_access: bool) -> Self {
+        self.ignore_access = ignore_access;
+        self
+    }
+
+    /// Only list record sets of this type, may be given several times
+    pub fn record_type_filter(mut self, record_type: RecordType) -> Self {
+        self.record_type_filter.push(record_type);
+        self
+    }
+
+    /// Only list record sets owned by the group with this id
+    pub fn record_owner_group_filter(mut self, group_id: &str) -> Self {
+        self.record_owner_group_filter = Some(group_id.to_string());
+        self
+    }
+
+    /// Order record sets by name, ascending unless set otherwise
+    pub fn name_sort(mut self, name_sort: NameSort) -> Self {
+        self.name_sort = Some(name_sort);
+        self
+    }
+
+    pub fn zones(&self) -> ApiRequest {
+        self.access_filter(self.request("/zones".to_string(), Some("nameFilter")))
+    }
+
+    pub fn zone_changes(&self, zone_id: &str) -> ApiRequest {
+        self.request(format!("/zones/{}/changes", zone_id), None)
+    }
+
+    pub fn record_sets(&self, zone_id: &str) -> ApiRequest {
+        self.record_set_filters(self.request(
+            format!("/zones/{}/recordsets", zone_id),
+            Some("recordNameFilter"),
+        ))
+    }
+
+    /// Search record sets across all zones, the name filter is required
+    pub fn record_sets_search(&self) -> ApiRequest {
+        self.record_set_filters(self.request("/recordsets".to_string(), Some("recordNameFilter")))
+    }
+
+    pub fn record_set_changes(&self, zone_id: &str) -> ApiRequest {
+        self.request(format!("/zones/{}/recordsetchanges", zone_id), None)
+    }
+
+    pub fn groups(&self) -> ApiRequest {
+        self.access_filter(self.request("/groups".to_string(), Some("groupNameFilter")))
+    }
+
+    pub fn batch_changes(&self, approval_status: Option<BatchChangeApprovalStatus>) -> ApiRequest {
+        let request =
+            self.access_filter(self.request("/zones/batchrecordchanges".to_string(), None));
+        match approval_status {
+            Some(approval_status) => request.param("approvalStatus", approval_status),
+            None => request,
+        }
+    }
+
+    fn access_filter(&self, request: ApiRequest) -> ApiRequest {
+        if self.ignore_access {
+            request.param("ignoreAccess", true)
+        } else {
+            request
+        }
+    }
+
+    fn record_set_filters(&self, mut request: ApiRequest) -> ApiRequest {
+        if !self.record_type_filter.is_empty() {
+            let types: Vec<_> = self.record_type_filter.iter().map(|t| t.as_str()).collect();
+            request = request.param("recordTypeFilter", types.join(","));
+        }
+        if let Some(group_id) = &self.record_owner_group_filter {
+            request = request.param("recordOwnerGroupFilter", group_id);
+        }
+        if let Some(name_sort) = &self.name_sort {
+            request = request.param("nameSort", name_sort);
+        }
+        request
+    }
+
+    fn request(&self, path: String, name_filter_param: Option<&str>) -> ApiRequest {
+        let mut request = ApiRequest::get(path);
+        if let (Some(param), Some(name_filter)) = (name_filter_param, &self.name_filter) {
+            request = request.param(param, name_filter);
+        }
+        if let Some(max_items) = self.max_items {
+            request = request.param("maxItems", max_items);
+        }
+        if let Some(start_from) = &self.start_from {
+            request = request.param("startFrom", start_from);
+        }
+        request
+    }
+}
+
+/// A single page of a listing endpoint which is paged with `startFrom`, `maxItems` and `nextId`
+pub trait Page: DeserializeOwned + Send {
+    type Item: Send;
+
+    fn next_id(&self) -> Option<&str>;
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+impl Page for Zones {
+    type Item = Zone;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Zone> {
+        self.zones
+    }
+}
+
+impl Page for ZoneChanges {
+    type Item = ZoneChange;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<ZoneChange> {
+        self.zone_changes
+    }
+}
+
+impl Page for Groups {
+    type Item = Group;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Group> {
+        self.groups
+    }
+}
+
+impl Page for BatchRecordChanges {
+    type Item = BatchRecordChangeSummary;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<BatchRecordChangeSummary> {
+        self.batch_changes
+    }
+}
+
+impl Page for RecordSetsResponse {
+    type Item = RecordSet;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<RecordSet> {
+        self.record_sets
+    }
+}
+
+impl Page for RecordSetSearchResponse {
+    type Item = RecordSetInfo;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<RecordSetInfo> {
+        self.record_sets
+    }
+}
+
+impl Page for RecordSetChanges {
+    type Item = RecordSetChange;
+
+    fn next_id(&self) -> Option<&str> {
+        self.next_id.as_deref()
+    }
+
+    fn into_items(self) -> Vec<RecordSetChange> {
+        self.record_set_changes
+    }
+}
+
+/// How a client's operations are awaited and how it waits between polls, so the helpers below,
+/// which make several requests, are written once as async code for both clients. The blocking
+/// `Client`'s futures have already finished by the time they are returned.
+pub(crate) trait Runtime: VinylDns {
+    fn wait<'a, T: Send + 'a>(
+        output: Self::Output<'a, T>,
+    ) -> impl Future<Output = Result<T>> + Send + 'a
+    where
+        Self: 'a;
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// Go through the items of a listing page by page until `f` breaks with a value, or they run
+/// out. Pages after the one it breaks on aren't requested
+async fn search<C: Runtime, P: Page, T>(
+    client: &C,
+    mut request: ApiRequest,
+    mut f: impl FnMut(P::Item) -> ControlFlow<Option<T>>,
+) -> Result<Option<T>> {
+    loop {
+        let page: P = C::wait(client.execute(request.clone())).await?;
+        let next_id = page.next_id().map(str::to_string);
+        for item in page.into_items() {
+            if let ControlFlow::Break(found) = f(item) {
+                return Ok(found);
+            }
+        }
+        match next_id {
+            Some(next_id) => request = request.param("startFrom", next_id),
+            None => return Ok(None),
+        }
+    }
+}
+
+fn not_found<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Err(e) => match e.downcast::<ClientError>() {
+            Ok(NotFound(_)) => Ok(None),
+            Ok(e) => Err(e.into()),
+            Err(e) => Err(e),
+        },
+        Ok(found) => Ok(Some(found)),
+    }
+}
+
+pub(crate) async fn zone_by_name_or_id<C: Runtime>(client: &C, zone: &str) -> Result<Zone> {
+    let by_name = not_found(C::wait(client.zone_by_name(zone)).await)?;
+    // names ending in a dot can't be ids
+    let by_id = if zone.ends_with('.') {
+        None
+    } else {
+        not_found(C::wait(client.zone(zone)).await)?
+    };
+    match (by_name, by_id) {
+        (Some(by_name), Some(by_id)) if by_name.id != by_id.id => {
+            Err(AmbiguousZone(zone.to_string(), by_name.name, by_id.name).into())
+        }
+        (Some(zone), _) | (None, Some(zone)) => Ok(zone),
+        (None, None) => Err(UnknownZone(zone.to_string()).into()),
+    }
+}
+
+pub(crate) async fn group_by_name_or_id<C: Runtime>(client: &C, group: &str) -> Result<Group> {
+    if let Some(group) = not_found(C::wait(client.group(group)).await)? {
+        return Ok(group);
+    }
+    let request = ListOptions::new().name_filter(group).groups();
+    search::<_, Groups, _>(client, request, |g| match g.name == group {
+        true => ControlFlow::Break(Some(g)),
+        false => ControlFlow::Continue(()),
+    })
+    .await?
+    .ok_or_else(|| UnknownGroup(group.to_string()).into())
+}
+
+/// The change a zone update response describes, to wait for
+pub(crate) fn zone_update_change(update: &ZoneUpdateResponse) -> ZoneChange {
+    ZoneChange {
+        zone: update.zone.clone(),
+        user_id: update.user_id.clone(),
+        change_type: update.change_type.clone(),
+        status: update.status.clone(),
+        created: update.created.clone(),
+        id: update.id.clone(),
+        system_message: None,
+    }
+}
+
+/// Poll a zone change until it completes. Changes are listed newest first, so once the change
+/// being waited for is known, paging stops at the first one older than it instead of going
+/// through the zone's whole history. A deleted zone's changes can't be listed once it is gone,
+/// so that counts as a delete completing
+pub(crate) async fn wait_for_zone_change<C: Runtime>(
+    client: &C,
+    zone_id: &str,
+    change_id: &str,
+    started: Option<&ZoneChange>,
+    options: &WaitOptions,
+) -> Result<ZoneChange> {
+    let created = |change: &ZoneChange| chrono::DateTime::parse_from_rfc3339(&change.created);
+    let since = started.and_then(|change| created(change).ok());
+    let request = ListOptions::new().zone_changes(zone_id);
+    let check = || async {
+        let change = search::<_, ZoneChanges, _>(client, request.clone(), |change| {
+            match (since, created(&change)) {
+                (Some(since), Ok(created)) if created < since => ControlFlow::Break(None),
+                _ if change.id == change_id => ControlFlow::Break(Some(change)),
+                _ => ControlFlow::Continue(()),
+            }
+        })
+        .await;
+        let change = match (change, started) {
+            (Err(e), Some(started))
+                if started.change_type == ZoneChangeType::Delete
+                    && matches!(e.downcast_ref::<ClientError>(), Some(NotFound(_))) =>
+            {
+                return Ok(Some(ZoneChange {
+                    status: ZoneChangeStatus::Synced,
+                    zone: Zone {
+                        status: Some(ZoneStatus::Deleted),
+                        ..started.zone.clone()
+                    },
+                    ..started.clone()
+                }));
+            }
+            (change, _) => change?,
+        };
+        match change {
+            Some(change) => match &change.status {
+                ZoneChangeStatus::Complete | ZoneChangeStatus::Synced => Ok(Some(change)),
+                ZoneChangeStatus::Failed => {
+                    Err(ChangeFailed(change.id, change.system_message.unwrap_or_default()).into())
+                }
+                _ => Ok(None),
+            },
+            None => Ok(None),
+        }
+    };
+    options.poll::<C, _, _>(change_id, check).await
+}
+
+pub(crate) async fn zone_sync_in_progress<C: Runtime>(
+    client: &C,
+    zone_id: &str,
+) -> Result<Option<ZoneChange>> {
+    // changes are newest first, so any pending ones come before the rest
+    let request = ListOptions::new().zone_changes(zone_id);
+    search::<_, ZoneChanges, _>(client, request, |change| {
+        if change.status != ZoneChangeStatus::Pending {
+            return ControlFlow::Break(None);
+        }
+        match change.change_type {
+            ZoneChangeType::Sync | ZoneChangeType::AutomatedSync => {
+                ControlFlow::Break(Some(change))
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    })
+    .await
+}
+
+pub(crate) async fn wait_for_record_set_change<C: Runtime>(
+    client: &C,
+    zone_id: &str,
+    record_set_id: &str,
+    change_id: &str,
+    options: &WaitOptions,
+) -> Result<RecordSetChange> {
+    let check = || async {
+        let change = C::wait(client.record_set_change(zone_id, record_set_id, change_id)).await?;
+        match &change.status {
+            RecordSetChangeStatus::Complete => Ok(Some(change)),
+            RecordSetChangeStatus::Failed => {
+                Err(ChangeFailed(change.id, change.system_message.unwrap_or_default()).into())
+            }
+            _ => Ok(None),
+        }
+    };
+    options.poll::<C, _, _>(change_id, check).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_list_options_request() {
+        let request = ListOptions::new()
+            .name_filter("ok")
+            .max_items(10)
+            .ignore_access(true)
+            .zones()
+            .param("maxItems", 20);
+        assert_eq!(request.path, "/zones");
+        let query: Vec<_> = request
+            .query
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            query,
+            vec![
+                ("nameFilter", "ok"),
+                ("ignoreAccess", "true"),
+                ("maxItems", "20")
+            ]
+        );
+
+        let request = ListOptions::new()
+            .name_filter("www")
+            .record_type_filter(RecordType::A)
+            .record_type_filter(RecordType::AAAA)
+            .record_owner_group_filter("group")
+            .name_sort(NameSort::Desc)
+            .record_sets("abc");
+        assert_eq!(request.path, "/zones/abc/recordsets");
+        let query: Vec<_> = request
+            .query
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            query,
+            vec![
+                ("recordNameFilter", "www"),
+                ("recordTypeFilter", "A,AAAA"),
+                ("recordOwnerGroupFilter", "group"),
+                ("nameSort", "DESC")
+            ]
+        );
+        // record set filters are ignored by other listings
+        assert!(ListOptions::new()
+            .record_type_filter(RecordType::A)
+            .zones()
+            .query
+            .is_empty());
+        // as is ignoreAccess by listings other than zones, groups and batch changes
+        let options = ListOptions::new().ignore_access(true);
+        assert!(options.record_sets("abc").query.is_empty());
+        assert!(options.record_sets_search().query.is_empty());
+        assert!(options.record_set_changes("abc").query.is_empty());
+        assert!(options.zone_changes("abc").query.is_empty());
+        assert_eq!(options.groups().query.len(), 1);
+        assert_eq!(options.batch_changes(None).query.len(), 1);
+    }
+
+    #[test]
+    fn test_sign() {
+        let signed = ApiRequest::get("/zones")
+            .param("nameFilter", "ok")
+            .sign(
+                "http://localhost:9000",
+                &Credentials::new("access", "secret"),
+                "us-west-2",
+                "vinyldns",
+            )
+            .unwrap();
+        assert_eq!(
+            signed.url.as_str(),
+            "http://localhost:9000/zones?nameFilter=ok"
+        );
+        assert_eq!(signed.headers["host"], "localhost:9000");
+        assert!(signed.headers["authorization"]
+            .to_str()
+            .unwrap()
+            .starts_with("AWS4-HMAC-SHA256 Credential=access/"));
+        assert!(signed.headers["authorization"]
+            .to_str()
+            .unwrap()
+            .contains("/us-west-2/vinyldns/aws4_request,"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let request = ApiRequest::delete("/groups/abc");
+        let err = decode::<Group>(&request, 404, "Group abc not found".to_string()).unwrap_err();
+        match err.downcast::<ClientError>() {
+            Ok(NotFound(r)) => assert_eq!(
+                r.to_string(),
+                "DELETE /groups/abc returned 404: Group abc not found"
+            ),
+            other => panic!("expected not found, got {:?}", other),
+        }
+    }
+}
@@ -1,7 +1,12 @@
-use serde::{Deserialize, Serialize};
+#![allow(non_local_definitions)] // failure's derive
+
+use failure::Fail;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
 use std::default::Default;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct ZoneConnection {
     pub name: String,
@@ -10,32 +15,68 @@ pub struct ZoneConnection {
     pub primary_server: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Access to a zone's record sets, for a user, a group, or everyone if neither is set
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ACLRule {
-    pub access_level: String,
-    pub description: String,
-    pub user_id: String,
-    pub group_id: String,
-    pub record_mask: String,
-    pub record_types: Vec<String>,
+    pub access_level: AccessLevel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    /// regex of the record names the rule applies to, all of them if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_mask: Option<String>,
+    /// the record types the rule applies to, all of them if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub record_types: Vec<RecordType>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+impl ACLRule {
+    /// A rule for every user
+    pub fn new(access_level: AccessLevel) -> Self {
+        ACLRule {
+            access_level,
+            description: None,
+            user_id: None,
+            group_id: None,
+            record_mask: None,
+            record_types: vec![],
+        }
+    }
+
+    pub fn for_user(access_level: AccessLevel, user_id: &str) -> Self {
+        ACLRule {
+            user_id: Some(user_id.to_string()),
+            ..ACLRule::new(access_level)
+        }
+    }
+
+    pub fn for_group(access_level: AccessLevel, group_id: &str) -> Self {
+        ACLRule {
+            group_id: Some(group_id.to_string()),
+            ..ACLRule::new(access_level)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct ZoneACL {
     pub rules: Vec<ACLRule>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct Zone {
     #[serde(skip_serializing_if = "String::is_empty")]
     pub name: String,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub email: String,
-    #[serde(skip_serializing_if = "String::is_empty")]
-    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ZoneStatus>,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub created: String,
     #[serde(skip_serializing_if = "String::is_empty")]
@@ -74,8 +115,8 @@ pub struct ZoneResponse {
 pub struct ZoneUpdateResponse {
     pub zone: Zone,
     pub user_id: String,
-    pub change_type: String,
-    pub status: String,
+    pub change_type: ZoneChangeType,
+    pub status: ZoneChangeStatus,
     pub created: String,
     pub id: String,
 }
@@ -84,6 +125,24 @@ pub struct ZoneUpdateResponse {
 #[serde(rename_all = "camelCase")]
 pub struct Zones {
     pub zones: Vec<Zone>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_filter: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
+    #[serde(default)]
+    pub ignore_access: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -92,51 +151,164 @@ pub struct ZoneChanges {
     #[serde(default)]
     pub zone_id: String,
     pub zone_changes: Vec<ZoneChange>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ZoneChange {
     pub zone: Zone,
     pub user_id: String,
-    pub change_type: String,
-    pub status: String,
+    pub change_type: ZoneChangeType,
+    pub status: ZoneChangeStatus,
     pub created: String,
     pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RecordSetChanges {
+    #[serde(default)]
+    pub zone_id: String,
     pub record_set_changes: Vec<RecordSetChange>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RecordSetChange {
     pub zone: Zone,
     pub record_set: RecordSet,
     pub user_id: String,
-    pub change_type: String,
-    pub status: String,
+    pub change_type: RecordSetChangeType,
+    pub status: RecordSetChangeStatus,
     pub created: String,
     pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
-#[serde(rename_all = "camelCase", default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(try_from = "RawRecordSet", into = "RawRecordSet")]
 pub struct RecordSet {
     pub id: String,
     pub zone_id: String,
     pub name: String,
-    #[serde(rename = "type")]
-    pub record_set_type: String,
-    pub status: String,
+    pub record_set_type: RecordType,
+    pub status: Option<RecordSetStatus>,
     pub created: String,
     pub updated: Option<String>,
     pub ttl: i32,
     pub account: String,
-    pub records: Vec<Record>,
+    pub owner_group_id: Option<String>,
+    pub records: Vec<RecordData>,
+}
+
+impl RecordSet {
+    /// A new record set of the given records, which must all be of the same type
+    pub fn new(name: &str, ttl: i32, records: Vec<RecordData>) -> Self {
+        RecordSet {
+            name: name.to_string(),
+            ttl,
+            record_set_type: records
+                .first()
+                .map(RecordData::record_type)
+                .unwrap_or_default(),
+            records,
+            ..Default::default()
+        }
+    }
+}
+
+/// RecordSet as it goes over the wire, with records in the loose `Record` form
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct RawRecordSet {
+    id: String,
+    zone_id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_set_type: String,
+    status: Option<RecordSetStatus>,
+    created: String,
+    updated: Option<String>,
+    ttl: i32,
+    account: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner_group_id: Option<String>,
+    records: Vec<Record>,
+}
+
+impl TryFrom<RawRecordSet> for RecordSet {
+    type Error = RecordDataError;
+
+    fn try_from(raw: RawRecordSet) -> Result<Self, Self::Error> {
+        let record_set_type: RecordType = raw.record_set_type.parse().unwrap();
+        let records = raw
+            .records
+            .into_iter()
+            .map(|record| RecordData::from_record(&record_set_type, record))
+            .collect::<Result<_, _>>()?;
+        Ok(RecordSet {
+            id: raw.id,
+            zone_id: raw.zone_id,
+            name: raw.name,
+            record_set_type,
+            status: raw.status,
+            created: raw.created,
+            updated: raw.updated,
+            ttl: raw.ttl,
+            account: raw.account,
+            owner_group_id: raw.owner_group_id,
+            records,
+        })
+    }
+}
+
+impl From<RecordSet> for RawRecordSet {
+    fn from(rs: RecordSet) -> Self {
+        RawRecordSet {
+            id: rs.id,
+            zone_id: rs.zone_id,
+            name: rs.name,
+            record_set_type: rs.record_set_type.to_string(),
+            status: rs.status,
+            created: rs.created,
+            updated: rs.updated,
+            ttl: rs.ttl,
+            account: rs.account,
+            owner_group_id: rs.owner_group_id,
+            records: rs.records.into_iter().map(Record::from).collect(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -145,10 +317,13 @@ pub struct RecordSetUpdateResponse {
     pub zone: Zone,
     pub record_set: RecordSet,
     pub id: String,
-    pub status: String,
+    pub status: RecordSetChangeStatus,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Loosely typed record data, with every field any record type might use.
+///
+/// Prefer `RecordData`, which this converts to and from.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 #[serde(rename_all = "camelCase", default)]
 pub struct Record {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -168,15 +343,15 @@ pub struct Record {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rname: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub serial: Option<i32>,
+    pub serial: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub refresh: Option<i32>,
+    pub refresh: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub retry: Option<i32>,
+    pub retry: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub expire: Option<i32>,
+    pub expire: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub minimum: Option<i32>,
+    pub minimum: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -188,12 +363,519 @@ pub struct Record {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub algorithm: Option<String>,
+    pub order: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regexp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "type")]
-    pub record_type: Option<String>,
+    pub record_type: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fingerprint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keytag: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digesttype: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+macro_rules! string_enum {
+    ($(#[$meta:meta])* pub enum $name:ident { $($(#[$vmeta:meta])* $variant:ident => $s:literal,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// a value this crate doesn't know about yet
+            Unknown(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $s,)*
+                    $name::Unknown(s) => s,
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(match s {
+                    $($s => $name::$variant,)*
+                    _ => $name::Unknown(s.to_string()),
+                })
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                Ok(s.parse().unwrap())
+            }
+        }
+    };
+}
+
+string_enum! {
+    #[allow(clippy::upper_case_acronyms)]
+    #[derive(Default)]
+    pub enum RecordType {
+        #[default]
+        A => "A",
+        AAAA => "AAAA",
+        CNAME => "CNAME",
+        DS => "DS",
+        MX => "MX",
+        NAPTR => "NAPTR",
+        NS => "NS",
+        PTR => "PTR",
+        SOA => "SOA",
+        SPF => "SPF",
+        SRV => "SRV",
+        SSHFP => "SSHFP",
+        TXT => "TXT",
+    }
+}
+
+string_enum! {
+    pub enum ZoneStatus {
+        Active => "Active",
+        Deleted => "Deleted",
+        PendingUpdate => "PendingUpdate",
+        PendingDelete => "PendingDelete",
+        Syncing => "Syncing",
+    }
+}
+
+string_enum! {
+    pub enum ZoneChangeType {
+        Create => "Create",
+        Update => "Update",
+        Delete => "Delete",
+        Sync => "Sync",
+        AutomatedSync => "AutomatedSync",
+    }
+}
+
+string_enum! {
+    pub enum ZoneChangeStatus {
+        Pending => "Pending",
+        Complete => "Complete",
+        Failed => "Failed",
+        Synced => "Synced",
+    }
+}
+
+string_enum! {
+    pub enum RecordSetStatus {
+        Active => "Active",
+        Inactive => "Inactive",
+        Pending => "Pending",
+        PendingUpdate => "PendingUpdate",
+        PendingDelete => "PendingDelete",
+    }
+}
+
+string_enum! {
+    pub enum RecordSetChangeType {
+        Create => "Create",
+        Update => "Update",
+        Delete => "Delete",
+    }
+}
+
+string_enum! {
+    pub enum RecordSetChangeStatus {
+        Pending => "Pending",
+        Complete => "Complete",
+        Failed => "Failed",
+    }
+}
+
+string_enum! {
+    pub enum NameSort {
+        Asc => "ASC",
+        Desc => "DESC",
+    }
+}
+
+string_enum! {
+    pub enum AccessLevel {
+        NoAccess => "NoAccess",
+        Read => "Read",
+        Write => "Write",
+        Delete => "Delete",
+    }
+}
+
+string_enum! {
+    pub enum GroupStatus {
+        Active => "Active",
+        Deleted => "Deleted",
+    }
+}
+
+string_enum! {
+    pub enum GroupChangeType {
+        Create => "Create",
+        Update => "Update",
+        Delete => "Delete",
+    }
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum ChangeInputType {
+        #[default]
+        Add => "Add",
+        DeleteRecordSet => "DeleteRecordSet",
+        DeleteRecord => "DeleteRecord",
+    }
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum SingleChangeStatus {
+        #[default]
+        Pending => "Pending",
+        Complete => "Complete",
+        Failed => "Failed",
+        NeedsReview => "NeedsReview",
+        Rejected => "Rejected",
+        Cancelled => "Cancelled",
+    }
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum BatchChangeStatus {
+        #[default]
+        Pending => "Pending",
+        PendingProcessing => "PendingProcessing",
+        PendingReview => "PendingReview",
+        Scheduled => "Scheduled",
+        Complete => "Complete",
+        Failed => "Failed",
+        PartialFailure => "PartialFailure",
+        Rejected => "Rejected",
+        Cancelled => "Cancelled",
+    }
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum BatchChangeApprovalStatus {
+        #[default]
+        AutoApproved => "AutoApproved",
+        PendingReview => "PendingReview",
+        ManuallyApproved => "ManuallyApproved",
+        ManuallyRejected => "ManuallyRejected",
+        Cancelled => "Cancelled",
+    }
+}
+
+/// The data of a single record, the shape of which depends on the record set's type
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordData {
+    A {
+        address: Ipv4Addr,
+    },
+    AAAA {
+        address: Ipv6Addr,
+    },
+    CNAME {
+        cname: String,
+    },
+    DS {
+        keytag: i32,
+        algorithm: i32,
+        digesttype: i32,
+        digest: String,
+    },
+    MX {
+        preference: i32,
+        exchange: String,
+    },
+    NAPTR {
+        order: i32,
+        preference: i32,
+        flags: String,
+        service: String,
+        regexp: String,
+        replacement: String,
+    },
+    NS {
+        nsdname: String,
+    },
+    PTR {
+        ptrdname: String,
+    },
+    SOA {
+        mname: String,
+        rname: String,
+        serial: i64,
+        refresh: i64,
+        retry: i64,
+        expire: i64,
+        minimum: i64,
+    },
+    SPF {
+        text: String,
+    },
+    SRV {
+        priority: i32,
+        weight: i32,
+        port: i32,
+        target: String,
+    },
+    SSHFP {
+        algorithm: i32,
+        typ: i32,
+        fingerprint: String,
+    },
+    TXT {
+        text: String,
+    },
+    /// data of a record type this crate doesn't know about, passed through as-is
+    Unknown(RecordType, Box<Record>),
+}
+
+#[derive(Debug, Fail)]
+pub enum RecordDataError {
+    #[fail(display = "{} record is missing field '{}'", _0, _1)]
+    MissingField(RecordType, &'static str),
+    #[fail(display = "{} record has invalid address '{}'", _0, _1)]
+    InvalidAddress(RecordType, String),
+}
+
+impl RecordData {
+    pub fn record_type(&self) -> RecordType {
+        match self {
+            RecordData::A { .. } => RecordType::A,
+            RecordData::AAAA { .. } => RecordType::AAAA,
+            RecordData::CNAME { .. } => RecordType::CNAME,
+            RecordData::DS { .. } => RecordType::DS,
+            RecordData::MX { .. } => RecordType::MX,
+            RecordData::NAPTR { .. } => RecordType::NAPTR,
+            RecordData::NS { .. } => RecordType::NS,
+            RecordData::PTR { .. } => RecordType::PTR,
+            RecordData::SOA { .. } => RecordType::SOA,
+            RecordData::SPF { .. } => RecordType::SPF,
+            RecordData::SRV { .. } => RecordType::SRV,
+            RecordData::SSHFP { .. } => RecordType::SSHFP,
+            RecordData::TXT { .. } => RecordType::TXT,
+            RecordData::Unknown(record_type, _) => record_type.clone(),
+        }
+    }
+
+    /// Interpret loosely typed record data as a record of the given type
+    pub fn from_record(record_type: &RecordType, record: Record) -> Result<Self, RecordDataError> {
+        let missing = |field| RecordDataError::MissingField(record_type.clone(), field);
+        macro_rules! field {
+            ($name:ident) => {
+                record.$name.ok_or_else(|| missing(stringify!($name)))?
+            };
+        }
+        let invalid = |address: &str| {
+            RecordDataError::InvalidAddress(record_type.clone(), address.to_string())
+        };
+
+        Ok(match record_type {
+            RecordType::A => {
+                let address = field!(address);
+                RecordData::A {
+                    address: address.parse().map_err(|_| invalid(&address))?,
+                }
+            }
+            RecordType::AAAA => {
+                let address = field!(address);
+                RecordData::AAAA {
+                    address: address.parse().map_err(|_| invalid(&address))?,
+                }
+            }
+            RecordType::CNAME => RecordData::CNAME {
+                cname: field!(cname),
+            },
+            RecordType::DS => RecordData::DS {
+                keytag: field!(keytag),
+                algorithm: field!(algorithm),
+                digesttype: field!(digesttype),
+                digest: field!(digest),
+            },
+            RecordType::MX => RecordData::MX {
+                preference: field!(preference),
+                exchange: field!(exchange),
+            },
+            RecordType::NAPTR => RecordData::NAPTR {
+                order: field!(order),
+                preference: field!(preference),
+                flags: field!(flags),
+                service: field!(service),
+                regexp: field!(regexp),
+                replacement: field!(replacement),
+            },
+            RecordType::NS => RecordData::NS {
+                nsdname: field!(nsdname),
+            },
+            RecordType::PTR => RecordData::PTR {
+                ptrdname: field!(ptrdname),
+            },
+            RecordType::SOA => RecordData::SOA {
+                mname: field!(mname),
+                rname: field!(rname),
+                serial: field!(serial),
+                refresh: field!(refresh),
+                retry: field!(retry),
+                expire: field!(expire),
+                minimum: field!(minimum),
+            },
+            RecordType::SPF => RecordData::SPF { text: field!(text) },
+            RecordType::SRV => RecordData::SRV {
+                priority: field!(priority),
+                weight: field!(weight),
+                port: field!(port),
+                target: field!(target),
+            },
+            RecordType::SSHFP => RecordData::SSHFP {
+                algorithm: field!(algorithm),
+                typ: field!(record_type),
+                fingerprint: field!(fingerprint),
+            },
+            RecordType::TXT => RecordData::TXT { text: field!(text) },
+            RecordType::Unknown(_) => RecordData::Unknown(record_type.clone(), Box::new(record)),
+        })
+    }
+}
+
+impl From<RecordData> for Record {
+    fn from(data: RecordData) -> Self {
+        match data {
+            RecordData::A { address } => Record {
+                address: Some(address.to_string()),
+                ..Default::default()
+            },
+            RecordData::AAAA { address } => Record {
+                address: Some(address.to_string()),
+                ..Default::default()
+            },
+            RecordData::CNAME { cname } => Record {
+                cname: Some(cname),
+                ..Default::default()
+            },
+            RecordData::DS {
+                keytag,
+                algorithm,
+                digesttype,
+                digest,
+            } => Record {
+                keytag: Some(keytag),
+                algorithm: Some(algorithm),
+                digesttype: Some(digesttype),
+                digest: Some(digest),
+                ..Default::default()
+            },
+            RecordData::MX {
+                preference,
+                exchange,
+            } => Record {
+                preference: Some(preference),
+                exchange: Some(exchange),
+                ..Default::default()
+            },
+            RecordData::NAPTR {
+                order,
+                preference,
+                flags,
+                service,
+                regexp,
+                replacement,
+            } => Record {
+                order: Some(order),
+                preference: Some(preference),
+                flags: Some(flags),
+                service: Some(service),
+                regexp: Some(regexp),
+                replacement: Some(replacement),
+                ..Default::default()
+            },
+            RecordData::NS { nsdname } => Record {
+                nsdname: Some(nsdname),
+                ..Default::default()
+            },
+            RecordData::PTR { ptrdname } => Record {
+                ptrdname: Some(ptrdname),
+                ..Default::default()
+            },
+            RecordData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => Record {
+                mname: Some(mname),
+                rname: Some(rname),
+                serial: Some(serial),
+                refresh: Some(refresh),
+                retry: Some(retry),
+                expire: Some(expire),
+                minimum: Some(minimum),
+                ..Default::default()
+            },
+            RecordData::SPF { text } | RecordData::TXT { text } => Record {
+                text: Some(text),
+                ..Default::default()
+            },
+            RecordData::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => Record {
+                priority: Some(priority),
+                weight: Some(weight),
+                port: Some(port),
+                target: Some(target),
+                ..Default::default()
+            },
+            RecordData::SSHFP {
+                algorithm,
+                typ,
+                fingerprint,
+            } => Record {
+                algorithm: Some(algorithm),
+                record_type: Some(typ),
+                fingerprint: Some(fingerprint),
+                ..Default::default()
+            },
+            RecordData::Unknown(_, record) => *record,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -205,12 +887,63 @@ pub struct RecordSetResponse {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RecordSetsResponse {
-    #[serde(default)]
-    pub next_id: String,
     pub record_sets: Vec<RecordSet>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// A record set found by a search across zones, along with the zone it is in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordSetInfo {
+    #[serde(flatten)]
+    pub record_set: RecordSet,
+    pub zone_name: String,
+    #[serde(default)]
+    pub zone_shared: bool,
+    #[serde(default)]
+    pub zone_admin_group_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_group_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordSetSearchResponse {
+    pub record_sets: Vec<RecordSetInfo>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_name_filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_sort: Option<NameSort>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct User {
     pub id: String,
@@ -225,9 +958,27 @@ pub struct User {
 #[serde(rename_all = "camelCase")]
 pub struct Groups {
     pub groups: Vec<Group>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_name_filter: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
+    #[serde(default)]
+    pub ignore_access: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct Group {
     #[serde(skip_serializing_if = "String::is_empty")]
@@ -237,8 +988,8 @@ pub struct Group {
     pub email: String,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub description: String,
-    #[serde(skip_serializing_if = "String::is_empty")]
-    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<GroupStatus>,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub created: String,
     pub members: Vec<User>,
@@ -257,12 +1008,12 @@ pub struct GroupMembers {
     pub members: Vec<User>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GroupChange {
     pub user_id: String,
     pub created: String,
-    pub change_type: String,
+    pub change_type: GroupChangeType,
     pub new_group: Group,
     pub old_group: Group,
 }
@@ -276,53 +1027,206 @@ pub struct GroupChanges {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchRecordChanges {
-    pub batch_changes: Vec<RecordChange>,
+    pub batch_changes: Vec<BatchRecordChangeSummary>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_from: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "string_or_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
+    #[serde(default)]
+    pub ignore_access: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_status: Option<BatchChangeApprovalStatus>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BatchRecordChangeSummary {
+    pub id: String,
+    pub user_id: String,
+    pub user_name: String,
+    pub comments: Option<String>,
+    pub created_timestamp: String,
+    pub total_changes: i32,
+    pub status: BatchChangeStatus,
+    pub owner_group_id: Option<String>,
+    pub owner_group_name: Option<String>,
+    pub approval_status: BatchChangeApprovalStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct RecordChange {
     pub id: String,
-    pub status: String,
-    pub change_type: String,
-    pub record_name: String,
-    pub ttl: i32,
+    pub status: SingleChangeStatus,
+    pub change_type: ChangeInputType,
+    pub input_name: String,
     #[serde(rename = "type")]
-    pub typ: String,
+    pub typ: RecordType,
+    pub ttl: Option<i32>,
+    pub record: Option<Record>,
+    pub record_name: String,
     pub zone_name: String,
-    pub input_name: String,
     pub zone_id: String,
-    pub total_changes: i32,
-    pub user_name: String,
-    pub comments: String,
-    pub user_id: String,
-    pub created_timestamp: String,
-    pub data: RecordData,
+    pub system_message: Option<String>,
+    pub record_change_id: Option<String>,
+    pub record_set_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct BatchRecordChangeUpdateResponse {
-    pub comments: String,
-    pub changes: Vec<RecordChange>,
+/// A single change submitted as part of a batch change
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecordChangeInput {
+    pub input_name: String,
+    pub change_type: ChangeInputType,
+    #[serde(rename = "type")]
+    pub typ: RecordType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<Record>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct RecordData {
-    pub address: String,
-    pub cname: String,
-    pub ptrdname: String,
+/// A change of a rejected batch change, with the reasons it failed validation
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecordChangeError {
+    #[serde(flatten)]
+    pub change: RecordChangeInput,
+    pub errors: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BatchRecordChangeInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_group_id: Option<String>,
+    pub changes: Vec<RecordChangeInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct BatchRecordChange {
     pub id: String,
     pub user_name: String,
     pub user_id: String,
-    pub status: String,
-    pub comments: String,
+    pub status: BatchChangeStatus,
+    pub comments: Option<String>,
     pub created_timestamp: String,
+    pub owner_group_id: Option<String>,
+    pub approval_status: BatchChangeApprovalStatus,
+    pub reviewer_id: Option<String>,
+    pub reviewer_user_name: Option<String>,
+    pub review_comment: Option<String>,
+    pub review_timestamp: Option<String>,
+    pub cancelled_timestamp: Option<String>,
     pub changes: Vec<RecordChange>,
 }
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BatchRecordChangeReview {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_comment: Option<String>,
+}
+
+/// a paging id, which VinylDNS sends as a string or a number depending on the listing
+fn string_or_number<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        String(String),
+        Number(i64),
+    }
+    Ok(Option::<Id>::deserialize(d)?.map(|id| match id {
+        Id::String(s) => s,
+        Id::Number(n) => n.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_string_enum() {
+        let statuses: Vec<RecordSetChangeStatus> =
+            serde_json::from_str(r#"["Complete", "Validated"]"#).unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                RecordSetChangeStatus::Complete,
+                RecordSetChangeStatus::Unknown("Validated".to_string())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&statuses).unwrap(),
+            r#"["Complete","Validated"]"#
+        );
+    }
+
+    #[test]
+    fn test_record_set_deserialize() {
+        let rs: RecordSet = serde_json::from_str(
+            r#"{"id":"rs1","zoneId":"z1","name":"mail","type":"MX","ttl":300,
+                "records":[{"preference":10,"exchange":"mx1.example.com."}]}"#,
+        )
+        .unwrap();
+        assert_eq!(rs.record_set_type, RecordType::MX);
+        assert_eq!(
+            rs.records,
+            vec![RecordData::MX {
+                preference: 10,
+                exchange: "mx1.example.com.".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_record_set_serialize() {
+        let rs = RecordSet::new(
+            "www",
+            300,
+            vec![RecordData::A {
+                address: "10.1.1.1".parse().unwrap(),
+            }],
+        );
+        let json = serde_json::to_value(&rs).unwrap();
+        assert_eq!(json["type"], "A");
+        assert_eq!(
+            json["records"],
+            serde_json::json!([{ "address": "10.1.1.1" }])
+        );
+    }
+
+    #[test]
+    fn test_record_set_missing_field() {
+        let err = serde_json::from_str::<RecordSet>(
+            r#"{"name":"mail","type":"MX","records":[{"exchange":"mx1."}]}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("missing field 'preference'"));
+    }
+
+    #[test]
+    fn test_record_set_unknown_type() {
+        let json = r#"{"name":"x","type":"LOC","records":[{"text":"whatever"}]}"#;
+        let rs: RecordSet = serde_json::from_str(json).unwrap();
+        assert_eq!(rs.record_set_type, RecordType::Unknown("LOC".to_string()));
+        assert_eq!(
+            serde_json::to_value(&rs).unwrap()["records"],
+            serde_json::json!([{ "text": "whatever" }])
+        );
+    }
+}
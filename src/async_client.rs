@@ -0,0 +1,393 @@
+use crate::api::{self, ApiRequest, Page, Runtime, VinylDns, DEFAULT_REGION, DEFAULT_SERVICE};
+use crate::api_types::*;
+use crate::client::{ClientBuilder, Profile, RetryPolicy, WaitOptions};
+use crate::credentials::{CredentialProvider, Credentials};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use crate::transport::{AsyncTransport, ResponseFuture};
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+/// A future returned by an `AsyncClient` operation
+pub type ApiFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// The non-blocking counterpart of `Client`, with the same operations through `VinylDns`,
+/// sending requests through `T`
+pub struct AsyncClient<T = reqwest::Client> {
+    pub host: String,
+    /// region requests are signed for
+    pub region: String,
+    /// service name requests are signed for
+    pub service: String,
+    pub(crate) credentials: Arc<dyn CredentialProvider>,
+    pub(crate) transport: T,
+    pub(crate) retry_policy: RetryPolicy,
+}
+
+impl AsyncClient {
+    /// Configure a client beyond its host and keys, finishing with `ClientBuilder::build_async`
+    pub fn builder(access_key: &str, secret_key: &str, host: &str) -> ClientBuilder {
+        ClientBuilder::new(access_key, secret_key, host)
+    }
+
+    /// Like `Client::from_env`
+    pub fn from_env() -> Result<Self> {
+        ClientBuilder::from_env()?.build_async()
+    }
+
+    /// Like `Client::from_config`
+    pub fn from_config(profile: Option<&str>, flags: Profile) -> Result<Self> {
+        ClientBuilder::from_config(profile, flags)?.build_async()
+    }
+
+    /// Like `Client::from_profile`
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        ClientBuilder::from_profile(profile)?.build_async()
+    }
+
+    pub fn new(access_key: &str, secret_key: &str, host: &str) -> Self {
+        AsyncClient::new_scoped(
+            access_key,
+            secret_key,
+            host,
+            DEFAULT_REGION,
+            DEFAULT_SERVICE,
+        )
+    }
+
+    /// A client signing requests for `region` and `service` instead of the defaults
+    pub fn new_scoped(
+        access_key: &str,
+        secret_key: &str,
+        host: &str,
+        region: &str,
+        service: &str,
+    ) -> Self {
+        AsyncClient {
+            host: host.to_string(),
+            region: region.to_string(),
+            service: service.to_string(),
+            credentials: Arc::new(Credentials::new(access_key, secret_key)),
+            transport: reqwest::Client::new(),
+            retry_policy: Default::default(),
+        }
+    }
+}
+
+impl<T: AsyncTransport> AsyncClient<T> {
+    /// Send requests through `transport` instead
+    pub fn with_transport<U: AsyncTransport>(self, transport: U) -> AsyncClient<U> {
+        AsyncClient {
+            host: self.host,
+            region: self.region,
+            service: self.service,
+            credentials: self.credentials,
+            transport,
+            retry_policy: self.retry_policy,
+        }
+    }
+
+    /// Sign requests for `region`
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+        self
+    }
+
+    /// Sign requests for `service`
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.service = service.to_string();
+        self
+    }
+
+    /// Get the keys to sign each request with from `provider`, like `Client::with_credentials`.
+    /// Providers are called from async code, so ones running commands or reading files block
+    /// the executor briefly whenever they refresh
+    pub fn with_credentials(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Arc::new(provider);
+        self
+    }
+
+    /// Replace the default policy for retrying failed requests
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Like `Client::zone_by_name_or_id`
+    pub async fn zone_by_name_or_id(&self, zone: &str) -> Result<Zone> {
+        api::zone_by_name_or_id(self, zone).await
+    }
+
+    /// Like `Client::group_by_name_or_id`
+    pub async fn group_by_name_or_id(&self, group: &str) -> Result<Group> {
+        api::group_by_name_or_id(self, group).await
+    }
+
+    /// Like `Client::wait_for_zone_change`
+    pub async fn wait_for_zone_change(
+        &self,
+        zone_id: &str,
+        change_id: &str,
+        options: &WaitOptions,
+    ) -> Result<ZoneChange> {
+        api::wait_for_zone_change(self, zone_id, change_id, None, options).await
+    }
+
+    /// Like `Client::wait_for_zone_update`
+    pub async fn wait_for_zone_update(
+        &self,
+        update: &ZoneUpdateResponse,
+        options: &WaitOptions,
+    ) -> Result<ZoneChange> {
+        let change = api::zone_update_change(update);
+        api::wait_for_zone_change(self, &update.zone.id, &update.id, Some(&change), options).await
+    }
+
+    /// Like `Client::wait_for_zone_sync`
+    pub async fn wait_for_zone_sync(
+        &self,
+        sync: &ZoneChange,
+        options: &WaitOptions,
+    ) -> Result<ZoneChange> {
+        api::wait_for_zone_change(self, &sync.zone.id, &sync.id, Some(sync), options).await
+    }
+
+    /// Like `Client::zone_sync_in_progress`
+    pub async fn zone_sync_in_progress(&self, zone_id: &str) -> Result<Option<ZoneChange>> {
+        api::zone_sync_in_progress(self, zone_id).await
+    }
+
+    /// Like `Client::wait_for_record_set_change`
+    pub async fn wait_for_record_set_change(
+        &self,
+        zone_id: &str,
+        record_set_id: &str,
+        change_id: &str,
+        options: &WaitOptions,
+    ) -> Result<RecordSetChange> {
+        api::wait_for_record_set_change(self, zone_id, record_set_id, change_id, options).await
+    }
+
+    /// Like `Client::wait_for_record_set_update`
+    pub async fn wait_for_record_set_update(
+        &self,
+        update: &RecordSetUpdateResponse,
+        options: &WaitOptions,
+    ) -> Result<RecordSetChange> {
+        self.wait_for_record_set_change(&update.zone.id, &update.record_set.id, &update.id, options)
+            .await
+    }
+
+    /// Make a single attempt at a request, signing it with the current time
+    fn send(&self, request: &ApiRequest) -> ResponseFuture<'_> {
+        let signed = self.credentials.credentials().and_then(|credentials| {
+            request.sign(&self.host, &credentials, &self.region, &self.service)
+        });
+        match signed {
+            Ok(signed) => self.transport.send(signed),
+            Err(e) => Box::pin(std::future::ready(Err(e))),
+        }
+    }
+}
+
+impl<T: AsyncTransport> VinylDns for AsyncClient<T> {
+    type Output<'a, O: 'a>
+        = ApiFuture<'a, O>
+    where
+        T: 'a;
+
+    fn execute<'a, R: DeserializeOwned + Send + 'a>(
+        &'a self,
+        request: ApiRequest,
+    ) -> ApiFuture<'a, R> {
+        Box::pin(async move {
+            self.retry_policy
+                .run::<Self, _, _>(&request, |request| self.send(request))
+                .await
+        })
+    }
+
+    fn then<'a, O: Send + 'a, U: Send + 'a>(
+        output: ApiFuture<'a, O>,
+        f: impl FnOnce(Result<O>) -> Result<U> + Send + 'a,
+    ) -> ApiFuture<'a, U> {
+        Box::pin(async move { f(output.await) })
+    }
+
+    fn all_pages<'a, P: Page + 'a>(
+        &'a self,
+        mut request: ApiRequest,
+    ) -> ApiFuture<'a, Vec<P::Item>> {
+        Box::pin(async move {
+            let mut items = vec![];
+            loop {
+                let page: P = self.execute(request.clone()).await?;
+                let next_id = page.next_id().map(str::to_string);
+                items.extend(page.into_items());
+                match next_id {
+                    Some(next_id) => request = request.param("startFrom", next_id),
+                    None => return Ok(items),
+                }
+            }
+        })
+    }
+}
+
+impl<T: AsyncTransport> Runtime for AsyncClient<T> {
+    fn wait<'a, O: Send + 'a>(
+        output: ApiFuture<'a, O>,
+    ) -> impl Future<Output = Result<O>> + Send + 'a
+    where
+        T: 'a,
+    {
+        output
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::ListOptions;
+    use crate::api_types::{Group, Groups};
+    use crate::client::ClientError;
+    use crate::mock_server::{MockServer, ACCESS_KEY, SECRET_KEY};
+    use crate::transport::MemoryTransport;
+    use reqwest::Method;
+
+    fn client(server: &MockServer) -> AsyncClient {
+        AsyncClient::new(ACCESS_KEY, SECRET_KEY, &server.host())
+            .with_retry_policy(RetryPolicy::none())
+    }
+
+    fn group(name: &str) -> Group {
+        Group {
+            name: name.to_string(),
+            email: "test@example.com".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let server = MockServer::start();
+        let client = client(&server);
+        let created = client.group_create(&group("ok-group")).await.unwrap();
+        assert_eq!(client.group(&created.id).await.unwrap().name, "ok-group");
+        match client
+            .group("nope")
+            .await
+            .unwrap_err()
+            .downcast::<ClientError>()
+        {
+            Ok(ClientError::NotFound(r)) => assert_eq!(r.path, "/groups/nope"),
+            other => panic!("expected not found, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_pages() {
+        let server = MockServer::start();
+        let client = client(&server);
+        for name in &["a", "b", "c"] {
+            client.group_create(&group(name)).await.unwrap();
+        }
+        let groups = client
+            .all_pages::<Groups>(ListOptions::new().max_items(2).groups())
+            .await
+            .unwrap();
+        let names: Vec<_> = groups.into_iter().map(|g| g.name).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let server = MockServer::start();
+        let client = client(&server).with_retry_policy(
+            RetryPolicy::new()
+                .max_attempts(3)
+                .initial_backoff(Duration::from_millis(1)),
+        );
+        server.fail_requests(503, 2);
+        assert!(client.groups().await.unwrap().is_empty());
+
+        server.fail_requests(503, 3);
+        match client.groups().await.unwrap_err().downcast::<ClientError>() {
+            Ok(ClientError::ServerError(r)) => assert_eq!(r.status, 503),
+            other => panic!("expected server error, got {:?}", other),
+        }
+        // POSTs may have been applied, so aren't retried
+        server.fail_requests(503, 1);
+        assert!(client.group_create(&group("ok-group")).await.is_err());
+        client.group_create(&group("ok-group")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_and_lookup() {
+        let server = MockServer::start();
+        server.pending_requests(3);
+        let client = client(&server);
+        let group = client.group_create(&group("ok-group")).await.unwrap();
+        assert_eq!(
+            client.group_by_name_or_id("ok-group").await.unwrap().id,
+            group.id
+        );
+
+        let update = client
+            .zone_create(&Zone {
+                name: "ok.".to_string(),
+                email: "test@example.com".to_string(),
+                admin_group_id: group.id,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(update.status, ZoneChangeStatus::Pending);
+        let options = WaitOptions::new().interval(Duration::from_millis(1));
+        let change = client
+            .wait_for_zone_update(&update, &options)
+            .await
+            .unwrap();
+        assert_eq!(change.status, ZoneChangeStatus::Synced);
+        let zone = client.zone_by_name_or_id("ok.").await.unwrap();
+        assert_eq!(zone.id, update.zone.id);
+        assert_eq!(zone.status, Some(ZoneStatus::Active));
+    }
+
+    #[tokio::test]
+    async fn test_memory_transport() {
+        let client = AsyncClient::new("access", "secret", "http://localhost:9000")
+            .with_retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+            .with_transport(
+                MemoryTransport::new()
+                    .respond(Method::GET, "/groups", 503, "unavailable")
+                    .respond(
+                        Method::GET,
+                        "/groups",
+                        200,
+                        r#"{"groups":[],"maxItems":100}"#,
+                    ),
+            );
+        assert!(client.groups().await.unwrap().is_empty());
+        assert_eq!(client.transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_build_async() {
+        let server = MockServer::start();
+        let client = AsyncClient::builder(ACCESS_KEY, SECRET_KEY, &server.host())
+            .timeout(Duration::from_secs(5))
+            .default_header("x-gateway-token", "abc")
+            .retry_policy(RetryPolicy::none())
+            .build_async()
+            .unwrap();
+        assert!(client.zones().await.unwrap().is_empty());
+    }
+}
@@ -5,7 +5,6 @@ mod task1 {
     // https://docs.aws.amazon.com/general/latest/gr/sigv4-create-canonical-request.html
 
     use log::trace;
-    use reqwest;
     use sha2::{Digest, Sha256};
     fn canonical_uri(url: &reqwest::Url) -> String {
         // 2. The canonical URI is the URI-encoded version of the absolute path component of the URI, which is everything in the URI from the HTTP host to the question mark character ("?") that begins the query string parameters (if any).
@@ -17,27 +16,26 @@ mod task1 {
     fn canonical_query_string(url: &reqwest::Url) -> String {
         // 3. Add the canonical query string, followed by a newline character. If the request does not include a query string, use an empty string (essentially, a blank line). The example request has the following query string.
 
-        // a. Sort the parameter names by character code point in ascending order. Parameters with duplicate names should be sorted by value. For example, a parameter name that begins with the uppercase letter F precedes a parameter name that begins with a lowercase letter b.
-        let mut query_pairs = url.query_pairs().collect::<Vec<_>>();
-        // PartialEq is implemented for (String, String) as dictionary sort for tuple, and the strings inside
-        query_pairs.sort();
         // b. URI-encode each parameter name and value according to the following rules:
         //    Do not URI-encode any of the unreserved characters that RFC 3986 defines: A-Z, a-z, 0-9, hyphen ( - ), underscore ( _ ), period ( . ), and tilde ( ~ ).
         //    Percent-encode all other characters with %XY, where X and Y are hexadecimal characters (0-9 and uppercase A-F). For example, the space character must be encoded as %20 (not using '+', as some encoding schemes do) and extended UTF-8 characters must be in the form %XY%ZA%BC.
         fn percent_encode(s: &str) -> String {
-            s.to_string()
-                .chars()
-                .map(|s| match s as u8 {
+            s.bytes()
+                .map(|b| match b {
                     b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
-                        format!("{}", s)
+                        (b as char).to_string()
                     }
-                    _ => format!("%{:02x}", s as u8),
+                    _ => format!("%{:02X}", b),
                 })
                 .collect::<String>()
         }
-        let query_pairs = query_pairs
-            .iter()
-            .map(|(k, v)| (percent_encode(k), percent_encode(v)));
+        // a. Sort the parameter names by character code point in ascending order. Parameters with duplicate names should be sorted by value. For example, a parameter name that begins with the uppercase letter F precedes a parameter name that begins with a lowercase letter b.
+        // the encoded names are what's sorted, so eg. "%5E" comes before "a"
+        let mut query_pairs = url
+            .query_pairs()
+            .map(|(k, v)| (percent_encode(&k), percent_encode(&v)))
+            .collect::<Vec<_>>();
+        query_pairs.sort();
         // c. Build the canonical query string by starting with the first parameter name in the sorted list.
         // d. For each parameter, append the URI-encoded parameter name, followed by the equals sign character (=), followed by the URI-encoded parameter value. Use an empty string for parameters that have no value.
         // e. Append the ampersand character (&) after each parameter value, except for the last value in the list.
@@ -50,50 +48,54 @@ mod task1 {
         query_string
     }
 
-    fn is_problematic_header(name: &str) -> bool {
-        // used to filter out problematic headers from canonical headers and signed headers
-        let name = name.to_lowercase();
-        (name.starts_with("x-amz-") && name != "x-amz-date") //|| name == "content-type"
+    /// Each header name, lowercase and sorted by character code, with its canonical value
+    fn canonical_header_values(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+        // To create the canonical headers list, convert all header names to lowercase and remove leading spaces and trailing spaces. Convert sequential spaces in the header value to a single space.
+        let canonical_value = |v: &reqwest::header::HeaderValue| {
+            String::from_utf8_lossy(v.as_bytes())
+                .split(' ')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        // Append a comma-separated list of values for that header. Do not sort the values in headers that have multiple values.
+        let mut headers = headers
+            .keys()
+            .map(|k| {
+                let values = headers
+                    .get_all(k)
+                    .iter()
+                    .map(canonical_value)
+                    .collect::<Vec<_>>();
+                (k.as_str().to_lowercase(), values.join(","))
+            })
+            .collect::<Vec<_>>();
+        headers.sort();
+        headers
     }
 
     fn canonical_headers(headers: &reqwest::header::HeaderMap) -> String {
         // 4. Add the canonical headers, followed by a newline character. The canonical headers consist of a list of all the HTTP headers that you are including with the signed request.
         // For HTTP/1.1 requests, you must include the host header at a minimum. Standard headers like content-type are optional.For HTTP/2 requests, you must include the :authority header instead of the host header. Different services might require other headers.
-        // To create the canonical headers list, convert all header names to lowercase and remove leading spaces and trailing spaces. Convert sequential spaces in the header value to a single space.
-        use regex;
+        // Every header given is signed, including x-amz-content-sha256 and x-amz-security-token, so only pass the headers which should be.
         // Build the canonical headers list by sorting the (lowercase) headers by character code and then iterating through the header names. Construct each header according to the following rules:
         //   Append the lowercase header name followed by a colon.
-        //   Append a comma-separated list of values for that header. Do not sort the values in headers that have multiple values.
+        //   Append a comma-separated list of values for that header.
         //   Append a new line ('\n').
-        let mut headers = headers
-            .iter()
-            .filter(|(k, _v)| !is_problematic_header(k.as_str()))
-            .map(|(k, v)| {
-                format!(
-                    "{}:{}\n",
-                    k.as_str().to_lowercase(),
-                    regex::Regex::new(r"  *")
-                        .unwrap()
-                        .replace_all(v.to_str().unwrap().trim(), " ")
-                )
-            })
-            .collect::<Vec<_>>();
-        // XXX: not combining multiple instances of header values with comma, so that's eventually going to be an issue...
-        headers.sort();
-        headers.join("")
+        canonical_header_values(headers)
+            .into_iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v))
+            .collect()
     }
 
     pub fn signed_headers(headers: &reqwest::header::HeaderMap) -> String {
         // 5. Add the signed headers, followed by a newline character. This value is the list of headers that you included in the canonical headers. By adding this list of headers, you tell AWS which headers in the request are part of the signing process and which ones AWS can ignore (for example, any additional headers added by a proxy) for purposes of validating the request.
         // For HTTP/1.1 requests, the host header must be included as a signed header. For HTTP/2 requests that include the :authority header instead of the host header, you must include the :authority header as a signed header. If you include a date or x-amz-date header, you must also include that header in the list of signed headers.
-        let mut headers = headers
-            .iter()
-            .filter(|(k, _v)| !is_problematic_header(k.as_str()))
-            .map(|(k, _v)| k.as_str().to_lowercase())
-            .collect::<Vec<_>>();
-        headers.sort();
-        headers.dedup();
-        headers.join(";")
+        canonical_header_values(headers)
+            .into_iter()
+            .map(|(k, _v)| k)
+            .collect::<Vec<_>>()
+            .join(";")
     }
 
     fn hashed_payload(payload: &[u8]) -> String {
@@ -188,13 +190,21 @@ mod task1 {
                         .parse()
                         .unwrap()
                 ),
-                "%5eval%24=%27%20%27&a=b&b=&b=c&c=azAZ09~-_"
-            )
+                "%5Eval%24=%27%20%27&a=b&b=&b=c&c=azAZ09~-_"
+            );
+            assert_eq!(
+                canonical_query_string(
+                    &"http://x.com/?ሴ=bar&Param2=value2&Param1=value1"
+                        .parse()
+                        .unwrap()
+                ),
+                "%E1%88%B4=bar&Param1=value1&Param2=value2"
+            );
         }
 
         fn build_headers(hdrs: Vec<&'static str>) -> reqwest::header::HeaderMap {
             use std::iter::FromIterator;
-            let headers = reqwest::header::HeaderMap::from_iter(hdrs.into_iter().map(|s| {
+            reqwest::header::HeaderMap::from_iter(hdrs.into_iter().map(|s| {
                 let i = s.find(":").unwrap();
                 let k = &s[..i];
                 let v = &s[i + 1..];
@@ -202,8 +212,7 @@ mod task1 {
                     reqwest::header::HeaderName::from_static(k),
                     reqwest::header::HeaderValue::from_static(v),
                 )
-            }));
-            headers
+            }))
         }
 
         #[test]
@@ -229,7 +238,21 @@ mod task1 {
             ]);
             assert_eq!(
                 canonical_headers(&headers),
-                "host:example.com\nspecial-header:special value\n"
+                "content-type:thingey\nhost:example.com\nspecial-header:special value,other value,z\n"
+            );
+        }
+
+        #[test]
+        fn test_canonical_headers_amz() {
+            let headers = build_headers(vec![
+                "host: example.com",
+                "x-amz-date:20150830T123600Z",
+                "x-amz-content-sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "x-amz-security-token:token",
+            ]);
+            assert_eq!(
+                signed_headers(&headers),
+                "host;x-amz-content-sha256;x-amz-date;x-amz-security-token"
             );
         }
 
@@ -251,7 +274,7 @@ mod task1 {
                 "special-header: other  value",
                 "special-header: z",
             ]);
-            assert_eq!(signed_headers(&headers), "host;special-header")
+            assert_eq!(signed_headers(&headers), "content-type;host;special-header")
         }
 
         #[test]
@@ -280,10 +303,11 @@ mod task1 {
                 r#"GET
 /
 Action=ListUsers&Version=2010-05-08
+content-type:application/x-www-form-urlencoded; charset=utf-8
 host:iam.amazonaws.com
 x-amz-date:20150830T123600Z
 
-host;x-amz-date
+content-type;host;x-amz-date
 e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"#
             );
         }
@@ -304,14 +328,14 @@ e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"#
             );
             assert_eq!(
                 hash,
-                "5599feeca6d065c7c80025038896f3f7f008849eacf307aa7d0cf8be7116cea6"
+                "f536975d06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"
             );
         }
     }
 }
 
 mod task2 {
-    use chrono::{DateTime, Datelike, Timelike, Utc};
+    use chrono::{DateTime, Utc};
     use log::trace;
 
     pub fn string_to_sign(
@@ -355,7 +379,7 @@ mod task2 {
         // 3. Append the credential scope value, followed by a newline character. This value is a string that includes the date, the region you are targeting, the service you are requesting, and a termination string ("aws4_request") in lowercase characters. The region and service name strings must be UTF-8 encoded.
         format!(
             "{}/{}/{}/aws4_request",
-            datetime.format("%Y%m%d").to_string(),
+            datetime.format("%Y%m%d"),
             region,
             service
         )
@@ -406,7 +430,7 @@ mod task3 {
 
     type HmacSha256 = Hmac<Sha256>;
 
-    fn hmac<'a>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
         let mut hmac = HmacSha256::new_varkey(key).unwrap();
         hmac.input(data);
         hmac.result().code().as_slice().to_owned()
@@ -489,16 +513,23 @@ f536975d06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"#
     }
 }
 
-pub fn prepare_request(request: &mut reqwest::Request, dt: DateTime<Utc>, body: &[u8]) {
+pub fn prepare_request(
+    url: &reqwest::Url,
+    headers: &mut reqwest::header::HeaderMap,
+    dt: DateTime<Utc>,
+    body: &[u8],
+) {
     use reqwest::header;
 
-    let fallback_host = request.url().domain().unwrap().to_owned();
+    let fallback_host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap(), port),
+        None => url.host_str().unwrap().to_owned(),
+    };
     let fallback_content_type = "application/x-www-form-urlencoded; charset=utf-8";
     let fallback_date = &dt.format("%Y%m%dT%H%M%SZ").to_string();
 
     {
         use sha2::{Digest, Sha256};
-        let headers = request.headers_mut();
 
         if !headers.contains_key(header::HOST) {
             headers.insert(header::HOST, fallback_host.parse().unwrap());
@@ -516,6 +547,7 @@ pub fn prepare_request(request: &mut reqwest::Request, dt: DateTime<Utc>, body:
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn auth_header(
     method: &reqwest::Method,
     url: &reqwest::Url,
@@ -550,7 +582,7 @@ mod test {
 
     fn build_headers(hdrs: Vec<&'static str>) -> reqwest::header::HeaderMap {
         use std::iter::FromIterator;
-        let headers = reqwest::header::HeaderMap::from_iter(hdrs.into_iter().map(|s| {
+        reqwest::header::HeaderMap::from_iter(hdrs.into_iter().map(|s| {
             let i = s.find(":").unwrap();
             let k = &s[..i];
             let v = &s[i + 1..];
@@ -558,13 +590,12 @@ mod test {
                 reqwest::header::HeaderName::from_static(k),
                 reqwest::header::HeaderValue::from_static(v),
             )
-        }));
-        headers
+        }))
     }
 
     #[test]
     fn test_auth_header() {
-        let expected = "Authorization: AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, SignedHeaders=host;x-amz-date, Signature=b2e4af44cfad96d9ffa3c5653674a927b9b0995c33de22e1f843745ce37c1d5e";
+        let expected = "Authorization: AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, SignedHeaders=content-type;host;x-amz-date, Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7";
         let auth_val = auth_header(
             &reqwest::Method::GET,
             &"https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08"
@@ -585,4 +616,115 @@ mod test {
 
         assert_eq!(format!("Authorization: {}", auth_val), expected);
     }
+
+    /// The signature of a request from the AWS SigV4 test suite
+    fn suite_signature(
+        method: &str,
+        path: &str,
+        headers: Vec<&'static str>,
+        body: &[u8],
+    ) -> String {
+        let mut all_headers = vec!["host:example.amazonaws.com", "x-amz-date:20150830T123600Z"];
+        all_headers.extend(headers);
+        let auth_val = auth_header(
+            &method.parse().unwrap(),
+            &format!("https://example.amazonaws.com{}", path)
+                .parse()
+                .unwrap(),
+            &build_headers(all_headers),
+            body,
+            Utc.ymd(2015, 8, 30).and_hms(12, 36, 0),
+            "us-east-1",
+            "service",
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+        );
+        auth_val.rsplit("Signature=").next().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_suite() {
+        let cases = vec![
+            (
+                "get-vanilla",
+                "GET",
+                "/",
+                vec![],
+                "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+            ),
+            (
+                "get-header-key-duplicate",
+                "GET",
+                "/",
+                vec![
+                    "my-header1:value2",
+                    "my-header1:value2",
+                    "my-header1:value1",
+                ],
+                "c9d5ea9f3f72853aea855b47ea873832890dbdd183b4468f858259531a5138ea",
+            ),
+            (
+                "get-header-value-trim",
+                "GET",
+                "/",
+                vec!["my-header1: value1", "my-header2: \"a   b   c\""],
+                "acc3ed3afb60bb290fc8d2dd0098b9911fcaa05412b367055dee359757a9c736",
+            ),
+            (
+                "get-vanilla-query-order-key-case",
+                "GET",
+                "/?Param2=value2&Param1=value1",
+                vec![],
+                "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+            ),
+            (
+                "get-vanilla-empty-query-key",
+                "GET",
+                "/?Param1=value1",
+                vec![],
+                "a67d582fa61cc504c4bae71f336f98b97f1ea3c7a6bfe1b6e45aec72011b9aeb",
+            ),
+            (
+                "get-unreserved",
+                "GET",
+                "/-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+                vec![],
+                "07ef7494c76fa4850883e2b006601f940f8a34d404d0cfa977f52a65bbf5f24f",
+            ),
+            (
+                "get-vanilla-utf8-query",
+                "GET",
+                "/?\u{1234}=bar",
+                vec![],
+                "2cdec8eed098649ff3a119c94853b13c643bcf08f8b0a1d91e12c9027818dd04",
+            ),
+            (
+                "post-vanilla",
+                "POST",
+                "/",
+                vec![],
+                "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b",
+            ),
+            (
+                "post-x-www-form-urlencoded",
+                "POST",
+                "/",
+                vec!["content-type:application/x-www-form-urlencoded"],
+                "ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a",
+            ),
+        ];
+        for (name, method, path, headers, expected) in cases {
+            let body: &[u8] = if name == "post-x-www-form-urlencoded" {
+                b"Param1=value1"
+            } else {
+                b""
+            };
+            assert_eq!(
+                suite_signature(method, path, headers, body),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
 }
@@ -1,257 +1,1520 @@
-#![allow(dead_code)]
+#![allow(non_local_definitions)] // failure's derive
 
+use crate::api::{self, Runtime};
 use crate::api_types::*;
-use crate::aws_sign;
+use crate::async_client::AsyncClient;
+use crate::config::Config;
+use crate::credentials::{
+    CommandCredentials, CredentialProvider, Credentials, EnvCredentials, FileCredentials,
+};
+use crate::transport;
 use failure::Fail;
 use log::*;
-use reqwest;
-use reqwest::header;
 use serde::de::DeserializeOwned;
-use serde_json;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
-pub struct Client {
-    pub access_key: String,
-    pub secret_key: String,
+pub use crate::api::{
+    ApiRequest, ListOptions, Page, SignedRequest, VinylDns, DEFAULT_REGION, DEFAULT_SERVICE,
+};
+pub use crate::config::{ConfigError, Profile};
+pub use crate::transport::{MemoryTransport, Response, Transport};
+
+/// A blocking VinylDNS client, sending requests through `T`
+pub struct Client<T = reqwest::blocking::Client> {
     pub host: String,
-    client: reqwest::Client,
+    /// region requests are signed for
+    pub region: String,
+    /// service name requests are signed for
+    pub service: String,
+    credentials: Arc<dyn CredentialProvider>,
+    transport: T,
+    retry_policy: RetryPolicy,
 }
 
 #[derive(Debug, Fail)]
 pub enum ClientError {
     #[fail(display = "missing environment variable: {}", _0)]
     MissingEnvironmentVariable(String),
+    #[fail(display = "missing setting: {}", _0)]
+    MissingSetting(String),
+    #[fail(display = "invalid setting: {}", _0)]
+    InvalidSetting(String),
     #[fail(display = "failed to parse value: {}", _0)]
     Parsing(String),
+    #[fail(display = "failed serializing request: {}", _0)]
+    Serializing(String),
     #[fail(display = "failed to execute request: {}", _0)]
-    Http(String),
+    Transport(String),
+    #[fail(display = "bad request: {}", _0)]
+    BadRequest(ErrorResponse),
+    #[fail(display = "unauthorized: {}", _0)]
+    Unauthorized(ErrorResponse),
+    #[fail(display = "forbidden: {}", _0)]
+    Forbidden(ErrorResponse),
+    #[fail(display = "not found: {}", _0)]
+    NotFound(ErrorResponse),
+    #[fail(display = "conflict: {}", _0)]
+    Conflict(ErrorResponse),
+    #[fail(display = "server error: {}", _0)]
+    ServerError(ErrorResponse),
+    #[fail(display = "unexpected response: {}", _0)]
+    Http(ErrorResponse),
     #[fail(display = "failed deserializing response: {}\n{}", _0, _1)]
     Deserializing(serde_json::error::Error, String),
+    #[fail(display = "batch change failed validation:\n{}", _0)]
+    BatchValidation(String, Vec<RecordChangeError>),
+    #[fail(display = "change {} failed: {}", _0, _1)]
+    ChangeFailed(String, String),
+    #[fail(display = "timed out waiting for change {} to complete", _0)]
+    Timeout(String),
+    #[fail(display = "no zone with name or id {}", _0)]
+    UnknownZone(String),
+    #[fail(display = "no group with name or id {}", _0)]
+    UnknownGroup(String),
+    #[fail(
+        display = "{} is ambiguous, it is the name of zone {} and the id of zone {}",
+        _0, _1, _2
+    )]
+    AmbiguousZone(String, String, String),
 }
 use crate::client::ClientError::*;
 
+impl ClientError {
+    pub(crate) fn from_response(response: ErrorResponse) -> Self {
+        match response.status {
+            400 | 422 => BadRequest(response),
+            401 => Unauthorized(response),
+            403 => Forbidden(response),
+            404 => NotFound(response),
+            409 => Conflict(response),
+            500..=599 => ServerError(response),
+            _ => Http(response),
+        }
+    }
+
+    /// The response the server rejected the request with, if it got that far
+    pub fn response(&self) -> Option<&ErrorResponse> {
+        match self {
+            BadRequest(r) | Unauthorized(r) | Forbidden(r) | NotFound(r) | Conflict(r)
+            | ServerError(r) | Http(r) => Some(r),
+            _ => None,
+        }
+    }
+}
+
+/// A non-2xx response from the server
+#[derive(Debug)]
+pub struct ErrorResponse {
+    pub status: u16,
+    pub method: String,
+    pub path: String,
+    /// the error message, or all validation errors joined together
+    pub message: String,
+    /// individual validation errors, if the server sent a list of them
+    pub errors: Vec<String>,
+    /// the raw response body
+    pub body: String,
+}
+
+impl ErrorResponse {
+    pub(crate) fn new(status: u16, method: &str, path: &str, body: String) -> Self {
+        // errors are usually plain text, but validation failures are sent as {"errors": [...]}
+        #[derive(serde::Deserialize)]
+        struct Errors {
+            errors: Vec<String>,
+        }
+        let errors = serde_json::from_str::<Errors>(&body)
+            .map(|e| e.errors)
+            .unwrap_or_default();
+        let message = if errors.is_empty() {
+            body.trim().to_string()
+        } else {
+            errors.join("; ")
+        };
+        ErrorResponse {
+            status,
+            method: method.to_string(),
+            path: path.to_string(),
+            message,
+            errors,
+            body,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} returned {}: {}",
+            self.method, self.path, self.status, self.message
+        )
+    }
+}
+
 type Result<R> = core::result::Result<R, failure::Error>;
 
 impl Client {
+    /// Configure a client beyond its host and keys
+    pub fn builder(access_key: &str, secret_key: &str, host: &str) -> ClientBuilder {
+        ClientBuilder::new(access_key, secret_key, host)
+    }
+
+    /// A client for `VINYLDNS_HOST`, `VINYLDNS_ACCESS_KEY` and `VINYLDNS_SECRET_KEY`, signing for
+    /// `VINYLDNS_REGION` and `VINYLDNS_SERVICE` if they are set. The keys are read again for
+    /// every request, so changes to them are picked up.
     pub fn from_env() -> Result<Self> {
-        fn e(n: &str) -> core::result::Result<String, ClientError> {
-            std::env::var(n).map_err(|e| MissingEnvironmentVariable(e.to_string()))
-        }
-        Ok(Client {
-            access_key: e("VINYLDNS_ACCESS_KEY")?,
-            secret_key: e("VINYLDNS_SECRET_KEY")?,
-            host: e("VINYLDNS_HOST")?,
-            client: reqwest::Client::new(),
-        })
+        ClientBuilder::from_env()?.build()
+    }
+
+    /// A client for the named profile of the config file, see `ClientBuilder::from_config`
+    pub fn from_config(profile: Option<&str>, flags: Profile) -> Result<Self> {
+        ClientBuilder::from_config(profile, flags)?.build()
+    }
+
+    /// A client with fully resolved settings, see `ClientBuilder::from_profile`
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        ClientBuilder::from_profile(profile)?.build()
     }
 
     pub fn new(access_key: &str, secret_key: &str, host: &str) -> Self {
+        Client::new_scoped(
+            access_key,
+            secret_key,
+            host,
+            DEFAULT_REGION,
+            DEFAULT_SERVICE,
+        )
+    }
+
+    /// A client signing requests for `region` and `service` instead of the defaults
+    pub fn new_scoped(
+        access_key: &str,
+        secret_key: &str,
+        host: &str,
+        region: &str,
+        service: &str,
+    ) -> Self {
         Client {
-            access_key: access_key.to_string(),
-            secret_key: secret_key.to_string(),
             host: host.to_string(),
-            client: reqwest::Client::new(),
+            region: region.to_string(),
+            service: service.to_string(),
+            credentials: Arc::new(Credentials::new(access_key, secret_key)),
+            transport: reqwest::blocking::Client::new(),
+            retry_policy: Default::default(),
         }
     }
+}
 
-    pub fn zones(&self) -> Result<Vec<Zone>> {
-        let zones: Zones = self.request("GET", "/zones", &[])?;
-        Ok(zones.zones)
+impl<T: Transport> Client<T> {
+    /// Send requests through `transport` instead
+    pub fn with_transport<U: Transport>(self, transport: U) -> Client<U> {
+        Client {
+            host: self.host,
+            region: self.region,
+            service: self.service,
+            credentials: self.credentials,
+            transport,
+            retry_policy: self.retry_policy,
+        }
+    }
+
+    /// Get the keys to sign each request with from `provider`, eg. to pick up rotated keys
+    pub fn with_credentials(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Arc::new(provider);
+        self
     }
 
-    pub fn zone(&self, id: &str) -> Result<Zone> {
-        let zone: ZoneResponse = self.request("GET", &format!("/zones/{}", id), &[])?;
-        Ok(zone.zone)
+    /// Sign requests for `region`
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+        self
     }
 
-    pub fn zone_create(&self, zone: &Zone) -> Result<ZoneUpdateResponse> {
-        let zone = serde_json::to_string(zone)?;
-        let response: ZoneUpdateResponse = self.request("POST", "/zones", &zone.as_bytes())?;
-        Ok(response)
+    /// Sign requests for `service`
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.service = service.to_string();
+        self
     }
 
-    pub fn zone_update(&self, id: &str, zone: &Zone) -> Result<ZoneUpdateResponse> {
-        let zone = serde_json::to_string(zone)?;
-        let response: ZoneUpdateResponse =
-            self.request("PUT", &format!("/zones/{}", id), &zone.as_bytes())?;
-        Ok(response)
+    /// Replace the default policy for retrying failed requests
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
     }
 
-    pub fn zone_delete(&self, id: &str) -> Result<ZoneUpdateResponse> {
-        let response: ZoneUpdateResponse =
-            self.request("DELETE", &format!("/zones/{}", id), &[])?;
-        Ok(response)
+    pub fn zones_iter(&self, options: &ListOptions) -> Paginated<'_, Zones, T> {
+        Paginated::new(self, options.zones())
     }
 
-    pub fn zone_changes(&self, id: &str) -> Result<Vec<ZoneChange>> {
-        let changes: ZoneChanges = self.request("GET", &format!("/zones/{}/changes", id), &[])?;
-        Ok(changes.zone_changes)
+    /// The zone with the name or id `zone`, returning `ClientError::UnknownZone` if there is
+    /// neither, and `ClientError::AmbiguousZone` if it names one zone and is the id of another
+    pub fn zone_by_name_or_id(&self, zone: &str) -> Result<Zone> {
+        now(api::zone_by_name_or_id(self, zone))
     }
 
-    pub fn record_sets(&self, zone_id: &str) -> Result<Vec<RecordSet>> {
-        // todo: iterator/stream response
-        let response: RecordSetsResponse =
-            self.request("GET", &format!("/zones/{}/recordsets", zone_id), &[])?;
-        Ok(response.record_sets)
+    /// The group with the id or name `group`, returning `ClientError::UnknownGroup` if there is
+    /// neither
+    pub fn group_by_name_or_id(&self, group: &str) -> Result<Group> {
+        now(api::group_by_name_or_id(self, group))
     }
 
-    pub fn record_set(&self, zone_id: &str, id: &str) -> Result<RecordSet> {
-        let record_set: RecordSet =
-            self.request("GET", &format!("/zones/{}/recordsets/{}", zone_id, id), &[])?;
-        Ok(record_set)
+    pub fn zone_changes_iter(&self, id: &str) -> Paginated<'_, ZoneChanges, T> {
+        Paginated::new(self, ListOptions::new().zone_changes(id))
     }
 
-    pub fn record_set_create(
+    /// Poll a zone change until it completes, returning `ClientError::ChangeFailed` if it fails
+    pub fn wait_for_zone_change(
         &self,
         zone_id: &str,
-        rs: &RecordSet,
-    ) -> Result<RecordSetUpdateResponse> {
-        let rs = serde_json::to_string(rs)?;
-        let response: RecordSetUpdateResponse = self.request(
-            "POST",
-            &format!("/zones/{}/recordsets", zone_id),
-            &rs.as_bytes(),
-        )?;
-        Ok(response)
+        change_id: &str,
+        options: &WaitOptions,
+    ) -> Result<ZoneChange> {
+        now(api::wait_for_zone_change(
+            self, zone_id, change_id, None, options,
+        ))
+    }
+
+    /// Wait for the change returned by `zone_create`, `zone_update` or `zone_delete`. A deleted
+    /// zone's changes can't be listed once it is gone, so that counts as the delete completing
+    pub fn wait_for_zone_update(
+        &self,
+        update: &ZoneUpdateResponse,
+        options: &WaitOptions,
+    ) -> Result<ZoneChange> {
+        let change = api::zone_update_change(update);
+        now(api::wait_for_zone_change(
+            self,
+            &update.zone.id,
+            &update.id,
+            Some(&change),
+            options,
+        ))
+    }
+
+    /// Wait for the change returned by `zone_sync`
+    pub fn wait_for_zone_sync(
+        &self,
+        sync: &ZoneChange,
+        options: &WaitOptions,
+    ) -> Result<ZoneChange> {
+        now(api::wait_for_zone_change(
+            self,
+            &sync.zone.id,
+            &sync.id,
+            Some(sync),
+            options,
+        ))
+    }
+
+    /// The sync of a zone which hasn't completed yet, if there is one
+    pub fn zone_sync_in_progress(&self, zone_id: &str) -> Result<Option<ZoneChange>> {
+        now(api::zone_sync_in_progress(self, zone_id))
     }
 
-    pub fn record_set_update(
+    pub fn record_sets_iter(
         &self,
         zone_id: &str,
-        id: &str,
-        rs: &RecordSet,
-    ) -> Result<RecordSetUpdateResponse> {
-        let rs = serde_json::to_string(rs)?;
-        let response: RecordSetUpdateResponse = self.request(
-            "PUT",
-            &format!("/zones/{}/recordsets/{}", zone_id, id),
-            &rs.as_bytes(),
-        )?;
-        Ok(response)
-    }
-
-    pub fn record_set_delete(&self, zone_id: &str, id: &str) -> Result<RecordSetUpdateResponse> {
-        let response: RecordSetUpdateResponse = self.request(
-            "DELETE",
-            &format!("/zones/{}/recordsets/{}", zone_id, id),
-            &[],
-        )?;
-        Ok(response)
-    }
-
-    pub fn record_set_changes(&self, zone_id: &str) -> Result<Vec<RecordSetChange>> {
-        // todo: iterate/stream response
-        let changes: RecordSetChanges =
-            self.request("GET", &format!("/zones/{}/recordsetchanges", zone_id), &[])?;
-        Ok(changes.record_set_changes)
-    }
-
-    pub fn record_set_change(
+        options: &ListOptions,
+    ) -> Paginated<'_, RecordSetsResponse, T> {
+        Paginated::new(self, options.record_sets(zone_id))
+    }
+
+    pub fn record_sets_search_iter(
+        &self,
+        options: &ListOptions,
+    ) -> Paginated<'_, RecordSetSearchResponse, T> {
+        Paginated::new(self, options.record_sets_search())
+    }
+
+    pub fn record_set_changes_iter(&self, zone_id: &str) -> Paginated<'_, RecordSetChanges, T> {
+        Paginated::new(self, ListOptions::new().record_set_changes(zone_id))
+    }
+
+    /// Poll a record set change until it completes, returning `ClientError::ChangeFailed` if
+    /// it fails
+    pub fn wait_for_record_set_change(
         &self,
         zone_id: &str,
         record_set_id: &str,
         change_id: &str,
+        options: &WaitOptions,
     ) -> Result<RecordSetChange> {
-        let change: RecordSetChange = self.request(
-            "GET",
-            &format!(
-                "/zones/{}/recordsets/{}/changes/{}",
-                zone_id, record_set_id, change_id
-            ),
-            &[],
-        )?;
-        Ok(change)
+        now(api::wait_for_record_set_change(
+            self,
+            zone_id,
+            record_set_id,
+            change_id,
+            options,
+        ))
+    }
+
+    /// Wait for the change returned by `record_set_create`, `record_set_update` or
+    /// `record_set_delete`
+    pub fn wait_for_record_set_update(
+        &self,
+        update: &RecordSetUpdateResponse,
+        options: &WaitOptions,
+    ) -> Result<RecordSetChange> {
+        self.wait_for_record_set_change(&update.zone.id, &update.record_set.id, &update.id, options)
+    }
+
+    pub fn groups_iter(&self, options: &ListOptions) -> Paginated<'_, Groups, T> {
+        Paginated::new(self, options.groups())
+    }
+
+    /// List batch changes, optionally only those with the given approval status
+    pub fn batch_changes_iter(
+        &self,
+        approval_status: Option<BatchChangeApprovalStatus>,
+    ) -> Paginated<'_, BatchRecordChanges, T> {
+        Paginated::new(self, ListOptions::new().batch_changes(approval_status))
+    }
+
+    /// Make a single attempt at a request, signing it with the current time
+    fn send(&self, request: &ApiRequest) -> Result<transport::Response> {
+        let credentials = self.credentials.credentials()?;
+        let signed = request.sign(&self.host, &credentials, &self.region, &self.service)?;
+        self.transport.send(signed)
+    }
+}
+
+impl<T: Transport> VinylDns for Client<T> {
+    type Output<'a, O: 'a>
+        = Result<O>
+    where
+        T: 'a;
+
+    fn execute<'a, R: DeserializeOwned + Send + 'a>(&'a self, request: ApiRequest) -> Result<R> {
+        now(self
+            .retry_policy
+            .run::<Self, _, _>(&request, |request| std::future::ready(self.send(request))))
+    }
+
+    fn then<'a, O: Send + 'a, U: Send + 'a>(
+        output: Result<O>,
+        f: impl FnOnce(Result<O>) -> Result<U> + Send + 'a,
+    ) -> Result<U> {
+        f(output)
     }
 
-    pub fn groups(&self) -> Result<Vec<Group>> {
-        let groups: Groups = self.request("GET", "/groups", &[])?;
-        Ok(groups.groups)
+    fn all_pages<'a, P: Page + 'a>(&'a self, request: ApiRequest) -> Result<Vec<P::Item>> {
+        Paginated::<P, T>::new(self, request).collect()
     }
+}
 
-    pub fn group_create(&self, group: &Group) -> Result<Group> {
-        let group = serde_json::to_string(group)?;
-        let group: Group = self.request("POST", "/groups", &group.as_bytes())?;
-        Ok(group)
+impl<T: Transport> Runtime for Client<T> {
+    fn wait<'a, O: Send + 'a>(output: Result<O>) -> impl Future<Output = Result<O>> + Send + 'a
+    where
+        T: 'a,
+    {
+        std::future::ready(output)
     }
 
-    pub fn group(&self, group_id: &str) -> Result<Group> {
-        let group: Group = self.request("GET", &format!("/groups/{}", group_id), &[])?;
-        Ok(group)
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        std::thread::sleep(duration);
+        std::future::ready(())
     }
+}
 
-    pub fn group_delete(&self, group_id: &str) -> Result<Group> {
-        let group: Group = self.request("DELETE", &format!("/groups/{}", group_id), &[])?;
-        Ok(group)
+/// Finish a future of the blocking client. Those only ever wait by blocking the thread, so they
+/// are done the first time they are polled
+fn now<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("blocking client futures never wait"),
     }
+}
+
+/// When and how often to retry a request which failed in a way that might be temporary, like
+/// a 409 because the zone already has a pending change, or a 503 from an overloaded server.
+///
+/// Requests the server rejected without applying (409 and 429) are always safe to retry, but
+/// for other failures a POST may already have taken effect, so by default they are only
+/// retried for idempotent methods.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    statuses: Vec<u16>,
+    retry_transport_errors: bool,
+    retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+            statuses: vec![409, 429, 500, 502, 503, 504],
+            retry_transport_errors: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
 
-    pub fn group_update(&self, group_id: &str, group: &Group) -> Result<Group> {
-        let group = serde_json::to_string(group)?;
-        let group: Group =
-            self.request("PUT", &format!("/groups/{}", group_id), &group.as_bytes())?;
-        Ok(group)
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Default::default()
     }
 
-    pub fn group_admins(&self, group_id: &str) -> Result<Vec<User>> {
-        let admins: GroupAdmins =
-            self.request("GET", &format!("/groups/{}/admins", group_id), &[])?;
-        Ok(admins.admins)
+    /// Never retry, every request is attempted exactly once
+    pub fn none() -> Self {
+        RetryPolicy::new().max_attempts(1)
     }
 
-    pub fn group_members(&self, group_id: &str) -> Result<Vec<User>> {
-        let members: GroupMembers =
-            self.request("GET", &format!("/groups/{}/members", group_id), &[])?;
-        Ok(members.members)
+    /// Total number of attempts, including the first
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
     }
 
-    pub fn group_activity(&self, group_id: &str) -> Result<GroupChanges> {
-        let activity: GroupChanges =
-            self.request("GET", &format!("/groups/{}/activiy", group_id), &[])?;
-        Ok(activity)
+    /// Time to wait before the first retry
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
     }
 
-    fn request<R: DeserializeOwned>(&self, method: &str, path: &str, body: &[u8]) -> Result<R> {
-        let dt = aws_sign::Utc::now();
+    /// Upper bound on the time to wait between attempts
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
 
-        trace!(
-            "{} {}\n{}",
-            method,
-            path,
-            std::str::from_utf8(body).unwrap_or_default()
+    /// Factor the backoff grows by after every attempt
+    ///
+    /// Panics unless `multiplier` is a finite number of at least 1.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        assert!(
+            multiplier.is_finite() && multiplier >= 1.0,
+            "backoff multiplier must be finite and at least 1, not {}",
+            multiplier
         );
+        self.multiplier = multiplier;
+        self
+    }
 
-        let mut req =
-            reqwest::Request::new(method.parse()?, format!("{}{}", self.host, path).parse()?);
-        req.headers_mut()
-            .insert(header::CONTENT_TYPE, "application/json".parse()?);
-        aws_sign::prepare_request(&mut req, dt, body);
-        let auth_val = aws_sign::auth_header(
-            req.method(),
-            req.url(),
-            req.headers(),
-            body,
-            dt,
-            "us-east-1",
-            "s3",
-            &self.access_key, //"testUserAccessKey",
-            &self.secret_key, //"testUserSecretKey",
+    /// Randomly shorten or lengthen each backoff by up to this fraction of it, so that many
+    /// clients failing at once don't all retry at once
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Response statuses which are retried
+    pub fn statuses(mut self, statuses: &[u16]) -> Self {
+        self.statuses = statuses.to_vec();
+        self
+    }
+
+    /// Whether to retry when the server could not be reached or the connection dropped
+    pub fn retry_transport_errors(mut self, retry_transport_errors: bool) -> Self {
+        self.retry_transport_errors = retry_transport_errors;
+        self
+    }
+
+    /// Whether to retry POSTs on failures which might mean the request was applied
+    pub fn retry_non_idempotent(mut self, retry_non_idempotent: bool) -> Self {
+        self.retry_non_idempotent = retry_non_idempotent;
+        self
+    }
+
+    pub(crate) fn should_retry(&self, method: &str, e: &failure::Error) -> bool {
+        let idempotent = method != "POST" && method != "PATCH";
+        match e.downcast_ref::<ClientError>() {
+            Some(Transport(_)) => {
+                self.retry_transport_errors && (idempotent || self.retry_non_idempotent)
+            }
+            Some(e) => match e.response() {
+                Some(response) if self.statuses.contains(&response.status) => {
+                    let rejected = response.status == 409 || response.status == 429;
+                    rejected || idempotent || self.retry_non_idempotent
+                }
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Make attempts at `request` with `send` until one succeeds, or fails in a way this policy
+    /// doesn't retry. Both clients run every request through this
+    pub(crate) async fn run<C: Runtime, R, F>(
+        &self,
+        request: &ApiRequest,
+        send: impl Fn(&ApiRequest) -> F,
+    ) -> Result<R>
+    where
+        R: DeserializeOwned,
+        F: Future<Output = Result<Response>>,
+    {
+        let method = request.method.as_str();
+        let mut attempt = 1;
+        loop {
+            let res = match send(request).await {
+                Ok(res) => api::decode(request, res.status, res.body),
+                Err(e) => Err(e),
+            };
+            match res {
+                Err(e) if attempt < self.max_attempts && self.should_retry(method, &e) => {
+                    let backoff = self.backoff(attempt);
+                    debug!(
+                        "attempt {} of {} {} failed, retrying in {:?}: {}",
+                        attempt, method, request.path, backoff, e
+                    );
+                    C::sleep(backoff).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        use std::collections::hash_map::RandomState;
+        use std::hash::{BuildHasher, Hasher};
+
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = scale(
+            self.initial_backoff,
+            self.multiplier.powi(exponent),
+            self.max_backoff,
+        );
+        // every RandomState is seeded differently, which is random enough for jitter
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        scale(
+            backoff,
+            1.0 + self.jitter * (2.0 * random - 1.0),
+            Duration::MAX,
+        )
+    }
+}
+
+/// `duration * factor`, capped at `max` while still in seconds, so that a large factor can't
+/// overflow a `Duration`. `factor` must not be negative.
+fn scale(duration: Duration, factor: f64, max: Duration) -> Duration {
+    let secs = duration.as_secs_f64() * factor;
+    if secs.is_nan() {
+        // a zero duration times a factor which overflowed to infinity
+        Duration::from_secs(0)
+    } else if secs >= max.as_secs_f64() {
+        max
+    } else {
+        Duration::from_secs_f64(secs)
+    }
+}
+
+/// How often and for how long to poll a pending change before giving up
+#[derive(Debug, Clone)]
+pub struct WaitOptions {
+    interval: Duration,
+    max_interval: Duration,
+    backoff: f64,
+    timeout: Duration,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(10),
+            backoff: 1.5,
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl WaitOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Time to wait before polling again after the first attempt
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Upper bound on the time between attempts
+    pub fn max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    /// Factor the interval grows by after every attempt, `1.0` polls at a fixed interval
+    ///
+    /// Panics unless `backoff` is a finite number of at least 1.
+    pub fn backoff(mut self, backoff: f64) -> Self {
+        assert!(
+            backoff.is_finite() && backoff >= 1.0,
+            "wait backoff must be finite and at least 1, not {}",
+            backoff
         );
-        req.headers_mut()
-            .insert(header::AUTHORIZATION, auth_val.parse()?);
-
-        trace!("{:?}", req.headers());
-        *req.body_mut() = Some(body.to_owned().into());
-
-        let resbody = self
-            .client
-            .execute(req)
-            .map_err(|e| Http(e.to_string()))
-            .and_then(|mut res| {
-                if !res.status().is_success() {
-                    Err(Http(res.text().unwrap_or(res.status().to_string())))
-                } else {
-                    res.text().map_err(|e| Http(e.to_string()))
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up with `ClientError::Timeout` after this long
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub(crate) async fn poll<C: Runtime, T, F>(
+        &self,
+        id: &str,
+        mut check: impl FnMut() -> F,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<Option<T>>>,
+    {
+        let start = Instant::now();
+        let mut interval = self.interval;
+        loop {
+            if let Some(done) = check().await? {
+                return Ok(done);
+            }
+            if start.elapsed() + interval > self.timeout {
+                return Err(Timeout(id.to_string()).into());
+            }
+            debug!(
+                "change {} still pending, polling again in {:?}",
+                id, interval
+            );
+            C::sleep(interval).await;
+            interval = scale(interval, self.backoff, self.max_interval);
+        }
+    }
+}
+
+/// Build a `reqwest::Client` or `reqwest::blocking::Client` with the settings of a
+/// `ClientBuilder`. Their builders have the same methods, but no trait in common
+macro_rules! http_client {
+    ($settings:expr, $builder:expr) => {{
+        let settings = &$settings;
+        let http = settings.http_settings()?;
+        let mut builder = $builder
+            .danger_accept_invalid_certs(settings.accept_invalid_certs)
+            .default_headers(http.headers)
+            .user_agent(settings.user_agent.as_str());
+        if let Some(timeout) = settings.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = settings.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = http.proxy {
+            builder = builder.proxy(proxy);
+        }
+        for cert in http.certs {
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some(identity) = http.identity {
+            builder = builder.identity(identity);
+        }
+        builder.build().map_err(invalid("http client"))?
+    }};
+}
+
+/// Configures the HTTP client behind a `Client` or `AsyncClient`, for instances behind proxies,
+/// private CAs or gateways requiring client certificates.
+///
+/// Files are read, and settings checked, by `build` and `build_async`.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    host: String,
+    credentials: Arc<dyn CredentialProvider>,
+    region: String,
+    service: String,
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    ca_bundles: Vec<PathBuf>,
+    client_cert: Option<(PathBuf, PathBuf)>,
+    accept_invalid_certs: bool,
+    headers: Vec<(String, String)>,
+    user_agent: String,
+}
+
+impl ClientBuilder {
+    pub fn new(access_key: &str, secret_key: &str, host: &str) -> Self {
+        ClientBuilder::with_provider(host, Arc::new(Credentials::new(access_key, secret_key)))
+    }
+
+    /// Settings from `VINYLDNS_HOST`, `VINYLDNS_REGION` and `VINYLDNS_SERVICE`, with keys read
+    /// from `VINYLDNS_ACCESS_KEY` and `VINYLDNS_SECRET_KEY` for every request
+    pub fn from_env() -> Result<Self> {
+        fn e(n: &str) -> Result<String> {
+            Ok(std::env::var(n).map_err(|e| MissingEnvironmentVariable(e.to_string()))?)
+        }
+        EnvCredentials.credentials()?;
+        Ok(
+            ClientBuilder::with_provider(&e("VINYLDNS_HOST")?, Arc::new(EnvCredentials))
+                .region(&e("VINYLDNS_REGION").unwrap_or_else(|_| DEFAULT_REGION.to_string()))
+                .service(&e("VINYLDNS_SERVICE").unwrap_or_else(|_| DEFAULT_SERVICE.to_string())),
+        )
+    }
+
+    /// Settings from the named profile of the config file, see `config` for where settings are
+    /// looked for. Without a name, `VINYLDNS_PROFILE` or else the default profile is used.
+    pub fn from_config(profile: Option<&str>, flags: Profile) -> Result<Self> {
+        let name = profile
+            .map(String::from)
+            .or_else(|| std::env::var("VINYLDNS_PROFILE").ok());
+        let profile = Config::load()?.resolve(name.as_deref(), flags, Profile::from_env()?)?;
+        ClientBuilder::from_profile(&profile)
+    }
+
+    /// Fully resolved settings. The host is required, and so are keys, or a
+    /// `credential_command` or `credentials_file` to get them from
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        let missing = |name: &str| {
+            MissingSetting(format!(
+                "{} (set VINYLDNS_{} or add {} to a profile)",
+                name,
+                name.to_uppercase(),
+                name
+            ))
+        };
+        let host = profile.host.as_deref().ok_or_else(|| missing("host"))?;
+        let credentials: Arc<dyn CredentialProvider> =
+            match (&profile.access_key, &profile.secret_key) {
+                (Some(access_key), Some(secret_key)) => Arc::new(Credentials {
+                    access_key: access_key.clone(),
+                    secret_key: secret_key.clone(),
+                }),
+                (Some(_), None) => return Err(missing("secret_key").into()),
+                _ => match (&profile.credential_command, &profile.credentials_file) {
+                    (Some(command), _) => Arc::new(CommandCredentials::new(command)),
+                    (None, Some(path)) => Arc::new(FileCredentials::new(path)),
+                    (None, None) => return Err(missing("access_key").into()),
+                },
+            };
+
+        let mut builder = ClientBuilder::with_provider(host, credentials);
+        if let Some(region) = &profile.region {
+            builder = builder.region(region);
+        }
+        if let Some(service) = &profile.service {
+            builder = builder.service(service);
+        }
+        if let Some(timeout) = profile.timeout() {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = profile.connect_timeout() {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = &profile.proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &profile.ca_bundle {
+            builder = builder.ca_bundle(path);
+        }
+        match (&profile.client_cert, &profile.client_key) {
+            (Some(cert), Some(key)) => builder = builder.client_cert(cert, key),
+            (None, None) => {}
+            _ => {
+                return Err(InvalidSetting(
+                    "client_cert and client_key must be set together".to_string(),
+                )
+                .into())
+            }
+        }
+        if let Some(accept_invalid_certs) = profile.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(accept_invalid_certs);
+        }
+        if let Some(user_agent) = &profile.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        Ok(builder)
+    }
+
+    /// A client for `host` getting the keys for each request from `credentials`
+    pub fn with_provider(host: &str, credentials: Arc<dyn CredentialProvider>) -> Self {
+        ClientBuilder {
+            host: host.to_string(),
+            credentials,
+            region: DEFAULT_REGION.to_string(),
+            service: DEFAULT_SERVICE.to_string(),
+            retry_policy: Default::default(),
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            ca_bundles: vec![],
+            client_cert: None,
+            accept_invalid_certs: false,
+            headers: vec![],
+            user_agent: concat!("vinyldns_rs/", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+
+    /// Sign requests for `region`
+    pub fn region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+        self
+    }
+
+    /// Sign requests for `service`
+    pub fn service(mut self, service: &str) -> Self {
+        self.service = service.to_string();
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Time allowed for a whole request, from connecting until the response is read. Unset,
+    /// reqwest's default of 30 seconds applies
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed for connecting
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Send every request through this HTTP(S) proxy, eg. `http://proxy.example.com:3128`.
+    /// Unset, the usual `HTTP_PROXY`/`HTTPS_PROXY` environment variables are used
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_string());
+        self
+    }
+
+    /// Also trust the certificates of this PEM file, which may hold several
+    pub fn ca_bundle(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_bundles.push(path.into());
+        self
+    }
+
+    /// Present this PEM certificate (chain), with its PKCS#8 PEM private key, to servers asking
+    /// for a client certificate
+    pub fn client_cert(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.client_cert = Some((cert.into(), key.into()));
+        self
+    }
+
+    /// Skip verifying the server's certificate. Anyone on the network can then read and change
+    /// requests, so only ever use this in lab setups
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Send this header with every request, eg. a token a gateway expects
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Defaults to `vinyldns_rs/<version>`
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let transport = http_client!(self, reqwest::blocking::Client::builder());
+        Ok(Client {
+            host: self.host,
+            credentials: self.credentials,
+            region: self.region,
+            service: self.service,
+            transport,
+            retry_policy: self.retry_policy,
+        })
+    }
+
+    /// Build an `AsyncClient` with these settings instead
+    pub fn build_async(self) -> Result<AsyncClient> {
+        let transport = http_client!(self, reqwest::Client::builder());
+        Ok(AsyncClient {
+            host: self.host,
+            credentials: self.credentials,
+            region: self.region,
+            service: self.service,
+            transport,
+            retry_policy: self.retry_policy,
+        })
+    }
+
+    /// The settings of the HTTP client which are read from files or may be invalid
+    fn http_settings(&self) -> Result<HttpSettings> {
+        fn read(setting: &str, path: &Path) -> Result<Vec<u8>> {
+            Ok(std::fs::read(path)
+                .map_err(|e| InvalidSetting(format!("{} {}: {}", setting, path.display(), e)))?)
+        }
+
+        if self.accept_invalid_certs {
+            warn!("accepting invalid certificates from {}", self.host);
+        }
+        let proxy = match &self.proxy {
+            Some(proxy) => Some(reqwest::Proxy::all(proxy.as_str()).map_err(invalid("proxy"))?),
+            None => None,
+        };
+        let mut certs = vec![];
+        for path in &self.ca_bundles {
+            certs.extend(
+                reqwest::Certificate::from_pem_bundle(&read("ca_bundle", path)?)
+                    .map_err(invalid("ca_bundle"))?,
+            );
+        }
+        let identity = match &self.client_cert {
+            Some((cert, key)) => Some(
+                reqwest::Identity::from_pkcs8_pem(
+                    &read("client_cert", cert)?,
+                    &read("client_key", key)?,
+                )
+                .map_err(invalid("client_cert"))?,
+            ),
+            None => None,
+        };
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            let invalid = || InvalidSetting(format!("header {}: {}", name, value));
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+                value.parse().map_err(|_| invalid())?,
+            );
+        }
+        Ok(HttpSettings {
+            proxy,
+            certs,
+            identity,
+            headers,
+        })
+    }
+}
+
+struct HttpSettings {
+    proxy: Option<reqwest::Proxy>,
+    certs: Vec<reqwest::Certificate>,
+    identity: Option<reqwest::Identity>,
+    headers: reqwest::header::HeaderMap,
+}
+
+fn invalid(setting: &str) -> impl Fn(reqwest::Error) -> ClientError {
+    let setting = setting.to_string();
+    move |e| InvalidSetting(format!("{}: {}", setting, e))
+}
+
+/// Lazily walks every page of a listing endpoint, only requesting the next page once all items
+/// of the current one have been consumed.
+///
+/// Iteration stops after the first error.
+pub struct Paginated<'a, P: Page, T = reqwest::blocking::Client> {
+    client: &'a Client<T>,
+    request: ApiRequest,
+    items: std::vec::IntoIter<P::Item>,
+    done: bool,
+}
+
+impl<'a, P: Page, T: Transport> Paginated<'a, P, T> {
+    fn new(client: &'a Client<T>, request: ApiRequest) -> Self {
+        Paginated {
+            client,
+            request,
+            items: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    /// Number of items requested per page, defaults to whatever the server picks
+    pub fn max_items(mut self, max_items: u32) -> Self {
+        self.request = self.request.param("maxItems", max_items);
+        self
+    }
+
+    /// Start listing from a `nextId` returned by a previous listing
+    pub fn start_from(mut self, start_from: &str) -> Self {
+        self.request = self.request.param("startFrom", start_from);
+        self
+    }
+}
+
+impl<'a, P: Page, T: Transport> Iterator for Paginated<'a, P, T> {
+    type Item = Result<P::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.client.execute::<P>(self.request.clone()) {
+                Ok(page) => {
+                    match page.next_id() {
+                        Some(next_id) => {
+                            self.request = self.request.clone().param("startFrom", next_id)
+                        }
+                        None => self.done = true,
+                    }
+                    self.items = page.into_items().into_iter();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
                 }
-            })?;
-        let res = serde_json::from_str(&resbody).map_err(|e| Deserializing(e, resbody))?;
-        Ok(res)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::MemoryTransport;
+    use reqwest::Method;
+
+    #[test]
+    fn test_error_response() {
+        let e = ClientError::from_response(ErrorResponse::new(
+            404,
+            "GET",
+            "/zones/abc",
+            "Zone with id abc does not exist\n".to_string(),
+        ));
+        match e {
+            NotFound(r) => {
+                assert_eq!(r.message, "Zone with id abc does not exist");
+                assert!(r.errors.is_empty());
+            }
+            other => panic!("expected not found, got {:?}", other),
+        }
+
+        let e = ClientError::from_response(ErrorResponse::new(
+            400,
+            "POST",
+            "/zones",
+            r#"{"errors":["Missing Zone.name","Missing Zone.email"]}"#.to_string(),
+        ));
+        let r = e.response().unwrap();
+        assert_eq!(r.errors, vec!["Missing Zone.name", "Missing Zone.email"]);
+        assert_eq!(
+            e.to_string(),
+            "bad request: POST /zones returned 400: Missing Zone.name; Missing Zone.email"
+        );
+    }
+
+    fn error(status: u16) -> failure::Error {
+        ClientError::from_response(ErrorResponse::new(status, "", "", String::new())).into()
+    }
+
+    #[test]
+    fn test_retry_policy_should_retry() {
+        let policy = RetryPolicy::new();
+        assert!(policy.should_retry("GET", &error(503)));
+        assert!(policy.should_retry("DELETE", &error(409)));
+        assert!(policy.should_retry("POST", &error(409)));
+        assert!(!policy.should_retry("POST", &error(503)));
+        assert!(!policy.should_retry("GET", &error(404)));
+        assert!(policy.should_retry("GET", &Transport(String::new()).into()));
+        assert!(!policy.should_retry("POST", &Transport(String::new()).into()));
+
+        let policy = policy.retry_non_idempotent(true).statuses(&[500]);
+        assert!(policy.should_retry("POST", &error(500)));
+        assert!(!policy.should_retry("GET", &error(503)));
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(1000))
+            .jitter(0.0);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_millis(1000));
+
+        let policy = policy.jitter(0.5);
+        for _ in 0..20 {
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_millis(50) && backoff <= Duration::from_millis(150));
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_backoff_overflow() {
+        let policy = RetryPolicy::new().max_attempts(100).jitter(0.0);
+        assert_eq!(policy.backoff(66), Duration::from_secs(10));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+
+        let policy = policy.initial_backoff(Duration::from_secs(0));
+        assert_eq!(policy.backoff(2000), Duration::from_secs(0));
+
+        let policy = RetryPolicy::new()
+            .max_backoff(Duration::MAX)
+            .multiplier(1e300);
+        assert!(policy.backoff(3) <= Duration::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff multiplier must be finite and at least 1")]
+    fn test_retry_policy_multiplier_nan() {
+        RetryPolicy::new().multiplier(f64::NAN);
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff multiplier must be finite and at least 1")]
+    fn test_retry_policy_multiplier_below_one() {
+        RetryPolicy::new().multiplier(0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "wait backoff must be finite and at least 1")]
+    fn test_wait_backoff_negative() {
+        WaitOptions::new().backoff(-2.0);
+    }
+
+    #[test]
+    fn test_wait_poll() {
+        let options = WaitOptions::new().interval(Duration::from_millis(1));
+        let mut attempts = 0;
+        let done = now(options.poll::<Client, _, _>("change", || {
+            attempts += 1;
+            std::future::ready(Ok(if attempts == 3 { Some(attempts) } else { None }))
+        }));
+        assert_eq!(done.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_wait_poll_timeout() {
+        let options = WaitOptions::new()
+            .interval(Duration::from_millis(5))
+            .timeout(Duration::from_millis(20));
+        let err = now(options.poll::<Client, (), _>("change", || std::future::ready(Ok(None))))
+            .unwrap_err();
+        match err.downcast::<ClientError>() {
+            Ok(Timeout(id)) => assert_eq!(id, "change"),
+            other => panic!("expected timeout, got {:?}", other),
+        }
+    }
+
+    fn memory_client(transport: MemoryTransport) -> Client<MemoryTransport> {
+        Client::new("access", "secret", "http://localhost:9000")
+            .with_retry_policy(RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+            .with_transport(transport)
+    }
+
+    #[test]
+    fn test_paginated() {
+        let client = memory_client(
+            MemoryTransport::new()
+                .respond(
+                    Method::GET,
+                    "/groups",
+                    200,
+                    r#"{"groups":[{"name":"a","email":"a@example.com"}],"nextId":"a","maxItems":1}"#,
+                )
+                .respond(
+                    Method::GET,
+                    "/groups",
+                    200,
+                    r#"{"groups":[{"name":"b","email":"b@example.com"}],"maxItems":1}"#,
+                ),
+        );
+        let groups: Vec<_> = client
+            .groups_iter(&ListOptions::new().max_items(1))
+            .map(|g| g.unwrap().name)
+            .collect();
+        assert_eq!(groups, vec!["a", "b"]);
+
+        let queries: Vec<_> = client
+            .transport
+            .requests()
+            .iter()
+            .map(|r| r.url.query().unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(queries, vec!["maxItems=1", "maxItems=1&startFrom=a"]);
+    }
+
+    #[test]
+    fn test_record_set_iters() {
+        let rs = |name: &str| format!(r#"{{"name":"{}","type":"A","ttl":300,"records":[]}}"#, name);
+        let change = |id: &str| {
+            format!(
+                r#"{{"zone":{{"id":"z"}},"recordSet":{},"userId":"u","changeType":"Create","status":"Complete","created":"2020-01-01T00:00:00Z","id":"{}"}}"#,
+                rs("www"),
+                id
+            )
+        };
+        let client = memory_client(
+            MemoryTransport::new()
+                .respond(
+                    Method::GET,
+                    "/zones/z/recordsets",
+                    200,
+                    &format!(
+                        r#"{{"recordSets":[{}],"nextId":"a","maxItems":1}}"#,
+                        rs("a")
+                    ),
+                )
+                .respond(
+                    Method::GET,
+                    "/zones/z/recordsets",
+                    200,
+                    &format!(r#"{{"recordSets":[{}],"maxItems":1}}"#, rs("b")),
+                )
+                // the server pages record set changes with numeric ids
+                .respond(
+                    Method::GET,
+                    "/zones/z/recordsetchanges",
+                    200,
+                    &format!(
+                        r#"{{"zoneId":"z","recordSetChanges":[{}],"nextId":1}}"#,
+                        change("c1")
+                    ),
+                )
+                .respond(
+                    Method::GET,
+                    "/zones/z/recordsetchanges",
+                    200,
+                    &format!(r#"{{"zoneId":"z","recordSetChanges":[{}]}}"#, change("c2")),
+                ),
+        );
+        let names: Vec<_> = client
+            .record_sets_iter("z", &ListOptions::new().max_items(1))
+            .map(|rs| rs.unwrap().name)
+            .collect();
+        assert_eq!(names, vec!["a", "b"]);
+        let ids: Vec<_> = client
+            .record_set_changes_iter("z")
+            .map(|change| change.unwrap().id)
+            .collect();
+        assert_eq!(ids, vec!["c1", "c2"]);
+
+        let queries: Vec<_> = client
+            .transport
+            .requests()
+            .iter()
+            .map(|r| r.url.query().unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(
+            queries,
+            vec!["maxItems=1", "maxItems=1&startFrom=a", "", "startFrom=1"]
+        );
+    }
+
+    #[test]
+    fn test_wait_for_zone_sync_paging() {
+        let change = |id: &str, created: &str, status: &str| {
+            format!(
+                r#"{{"zone":{{"id":"z"}},"userId":"u","changeType":"Sync","status":"{}","created":"{}","id":"{}"}}"#,
+                status, created, id
+            )
+        };
+        let page = |changes: &[String], next_id: Option<&str>| {
+            format!(
+                r#"{{"zoneId":"z","zoneChanges":[{}],"nextId":{},"maxItems":2}}"#,
+                changes.join(","),
+                next_id.map_or("null".to_string(), |id| format!("{:?}", id))
+            )
+        };
+        let client = memory_client(
+            MemoryTransport::new()
+                // the sync isn't listed yet, but an older change shows it never will be on a
+                // later page
+                .respond(
+                    Method::GET,
+                    "/zones/z/changes",
+                    200,
+                    &page(
+                        &[
+                            change("c", "2020-01-01T00:00:02Z", "Synced"),
+                            change("a", "2020-01-01T00:00:00Z", "Synced"),
+                        ],
+                        Some("a"),
+                    ),
+                )
+                .respond(
+                    Method::GET,
+                    "/zones/z/changes",
+                    200,
+                    &page(&[change("b", "2020-01-01T00:00:01Z", "Synced")], None),
+                ),
+        );
+        let sync: ZoneChange =
+            serde_json::from_str(&change("b", "2020-01-01T00:00:01Z", "Pending")).unwrap();
+        let options = WaitOptions::new().interval(Duration::from_millis(1));
+        let synced = client.wait_for_zone_sync(&sync, &options).unwrap();
+        assert_eq!(synced.status, ZoneChangeStatus::Synced);
+        let queries: Vec<_> = client
+            .transport
+            .requests()
+            .iter()
+            .map(|r| r.url.query().unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(queries, vec!["", ""]);
+    }
+
+    #[test]
+    fn test_batch_change_validation() {
+        let client = memory_client(MemoryTransport::new().respond(
+            Method::POST,
+            "/zones/batchrecordchanges",
+            400,
+            r#"[
+                {"changeType":"Add","inputName":"www.nope.","type":"A","ttl":300,
+                 "errors":["Zone Discovery Failed: zone for \"www.nope.\" does not exist in VinylDNS."]},
+                {"changeType":"DeleteRecordSet","inputName":"mail.ok.","type":"CNAME","errors":[]}
+            ]"#,
+        ));
+        let batch = BatchRecordChangeInput {
+            changes: vec![RecordChangeInput {
+                input_name: "www.nope.".to_string(),
+                ttl: Some(300),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let err = client.batch_change_create(&batch, false).unwrap_err();
+        match err.downcast::<ClientError>() {
+            Ok(BatchValidation(summary, errors)) => {
+                assert_eq!(
+                    summary,
+                    "Add A www.nope.: Zone Discovery Failed: zone for \"www.nope.\" does not exist in VinylDNS."
+                );
+                assert_eq!(errors.len(), 2);
+                assert_eq!(
+                    errors[1].change.change_type,
+                    ChangeInputType::DeleteRecordSet
+                );
+                assert!(errors[1].errors.is_empty());
+            }
+            other => panic!("expected batch validation, got {:?}", other),
+        }
+        let query = client.transport.requests()[0]
+            .url
+            .query()
+            .map(str::to_string);
+        assert_eq!(query.as_deref(), Some("allowManualReview=false"));
+
+        // a 400 without per-change errors is a plain bad request
+        let client = memory_client(MemoryTransport::new().respond(
+            Method::POST,
+            "/zones/batchrecordchanges",
+            400,
+            "Batch change contained no changes",
+        ));
+        match client
+            .batch_change_create(&batch, true)
+            .unwrap_err()
+            .downcast::<ClientError>()
+        {
+            Ok(BadRequest(r)) => assert_eq!(r.message, "Batch change contained no changes"),
+            other => panic!("expected bad request, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_batch_changes_paginated() {
+        // the server sends numeric offsets for startFrom and nextId
+        let client = memory_client(
+            MemoryTransport::new()
+                .respond(
+                    Method::GET,
+                    "/zones/batchrecordchanges",
+                    200,
+                    r#"{"batchChanges":[{"id":"a","totalChanges":1}],"nextId":1,"maxItems":1}"#,
+                )
+                .respond(
+                    Method::GET,
+                    "/zones/batchrecordchanges",
+                    200,
+                    r#"{"batchChanges":[{"id":"b","totalChanges":2}],"startFrom":1,"maxItems":1}"#,
+                ),
+        );
+        let ids: Vec<_> = client
+            .batch_changes_iter(None)
+            .map(|b| b.unwrap().id)
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        let queries: Vec<_> = client
+            .transport
+            .requests()
+            .iter()
+            .map(|r| r.url.query().unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(queries, vec!["", "startFrom=1"]);
+
+        let page: BatchRecordChanges = serde_json::from_str(
+            r#"{"batchChanges":[],"startFrom":"2","nextId":null,"maxItems":100}"#,
+        )
+        .unwrap();
+        assert_eq!(page.start_from.as_deref(), Some("2"));
+        assert_eq!(page.next_id, None);
+    }
+
+    #[test]
+    fn test_builder() {
+        let server = crate::mock_server::MockServer::start();
+        let client = Client::builder("testUserAccessKey", "testUserSecretKey", &server.host())
+            .timeout(Duration::from_secs(5))
+            .connect_timeout(Duration::from_secs(1))
+            .default_header("x-gateway-token", "abc")
+            .user_agent("test")
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert!(client.zones().unwrap().is_empty());
+
+        let invalid = |builder: ClientBuilder| match builder.build() {
+            Err(e) => e.downcast::<ClientError>().unwrap().to_string(),
+            Ok(_) => panic!("expected an invalid setting"),
+        };
+        let builder = Client::builder("access", "secret", "http://localhost:9000");
+        assert_eq!(
+            invalid(builder.clone().default_header("bad header", "x")),
+            "invalid setting: header bad header: x"
+        );
+        assert!(invalid(builder.clone().ca_bundle("/nonexistent.pem"))
+            .starts_with("invalid setting: ca_bundle /nonexistent.pem"));
+        assert!(invalid(builder.proxy("not a url")).starts_with("invalid setting: proxy"));
+    }
+
+    #[test]
+    fn test_execute_retries() {
+        let client = memory_client(
+            MemoryTransport::new()
+                .respond(Method::GET, "/groups/abc", 503, "try again")
+                .respond(
+                    Method::GET,
+                    "/groups/abc",
+                    200,
+                    r#"{"name":"ok","email":"ok@example.com"}"#,
+                )
+                .respond(Method::DELETE, "/groups/abc", 503, "try again"),
+        );
+        assert_eq!(client.group("abc").unwrap().name, "ok");
+        assert_eq!(client.transport.requests().len(), 2);
+
+        let err = client.group_delete("abc").unwrap_err();
+        match err.downcast::<ClientError>() {
+            Ok(ServerError(r)) => assert_eq!(r.status, 503),
+            other => panic!("expected server error, got {:?}", other),
+        }
+        assert_eq!(client.transport.requests().len(), 5);
     }
 }
@@ -0,0 +1,245 @@
+//! Named profiles of connection settings, read from `~/.config/vinyldns/config.toml`
+//!
+//! ```toml
+//! [default]
+//! host = "http://localhost:9000"
+//! access_key = "testUserAccessKey"
+//! secret_key = "testUserSecretKey"
+//!
+//! [prod]
+//! host = "https://vinyldns.example.com"
+//! region = "us-west-2"
+//! timeout = 30
+//! ca_bundle = "/etc/ssl/corp-ca.pem"
+//! ```
+//!
+//! Settings are taken from flags first, then `VINYLDNS_*` environment variables, then the
+//! profile, then defaults.
+
+#![allow(non_local_definitions)] // failure's derive
+
+use crate::client::ClientError;
+use crate::credentials::Secret;
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+/// The profile used when none is given
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Connection settings, any of which may be unset so that several sources can be layered
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub host: Option<String>,
+    pub access_key: Option<String>,
+    pub secret_key: Option<Secret>,
+    /// command printing credentials, used when the keys aren't set
+    pub credential_command: Option<String>,
+    /// file of credentials, read again when it changes, used when the keys aren't set
+    pub credentials_file: Option<PathBuf>,
+    /// region requests are signed for
+    pub region: Option<String>,
+    /// service name requests are signed for
+    pub service: Option<String>,
+    /// seconds to wait for a whole request
+    pub timeout: Option<u64>,
+    /// seconds to wait for a connection
+    pub connect_timeout: Option<u64>,
+    /// HTTP(S) proxy to send requests through
+    pub proxy: Option<String>,
+    /// PEM file of extra certificates to trust
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM certificate to present when the server asks for one, along with `client_key`
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM private key of `client_cert`
+    pub client_key: Option<PathBuf>,
+    /// skip certificate verification, only ever for lab setups
+    pub accept_invalid_certs: Option<bool>,
+    pub user_agent: Option<String>,
+}
+
+/// Every profile in a config file, by name
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(transparent)]
+pub struct Config {
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Fail)]
+pub enum ConfigError {
+    #[fail(display = "failed to read {}: {}", _0, _1)]
+    Read(String, std::io::Error),
+    #[fail(display = "invalid config {}: {}", _0, _1)]
+    Parse(String, toml::de::Error),
+    #[fail(display = "no profile named {} in {}", _0, _1)]
+    UnknownProfile(String, String),
+}
+
+impl Profile {
+    /// Settings from `VINYLDNS_HOST`, `VINYLDNS_ACCESS_KEY`, `VINYLDNS_SECRET_KEY`,
+    /// `VINYLDNS_CREDENTIAL_COMMAND`, `VINYLDNS_CREDENTIALS_FILE`,
+    /// `VINYLDNS_REGION`, `VINYLDNS_SERVICE`, `VINYLDNS_TIMEOUT`, `VINYLDNS_CONNECT_TIMEOUT`,
+    /// `VINYLDNS_PROXY`, `VINYLDNS_CA_BUNDLE`, `VINYLDNS_CLIENT_CERT`, `VINYLDNS_CLIENT_KEY`,
+    /// `VINYLDNS_ACCEPT_INVALID_CERTS` and `VINYLDNS_USER_AGENT`
+    pub fn from_env() -> Result<Self> {
+        fn e(n: &str) -> Option<String> {
+            std::env::var(n).ok().filter(|v| !v.is_empty())
+        }
+        fn parse<T: std::str::FromStr>(n: &str) -> Result<Option<T>> {
+            e(n).map(|v| {
+                v.parse()
+                    .map_err(|_| ClientError::Parsing(format!("{}={}", n, v)).into())
+            })
+            .transpose()
+        }
+        Ok(Profile {
+            host: e("VINYLDNS_HOST"),
+            access_key: e("VINYLDNS_ACCESS_KEY"),
+            secret_key: e("VINYLDNS_SECRET_KEY").map(Secret::new),
+            credential_command: e("VINYLDNS_CREDENTIAL_COMMAND"),
+            credentials_file: e("VINYLDNS_CREDENTIALS_FILE").map(PathBuf::from),
+            region: e("VINYLDNS_REGION"),
+            service: e("VINYLDNS_SERVICE"),
+            timeout: parse("VINYLDNS_TIMEOUT")?,
+            connect_timeout: parse("VINYLDNS_CONNECT_TIMEOUT")?,
+            proxy: e("VINYLDNS_PROXY"),
+            ca_bundle: e("VINYLDNS_CA_BUNDLE").map(PathBuf::from),
+            client_cert: e("VINYLDNS_CLIENT_CERT").map(PathBuf::from),
+            client_key: e("VINYLDNS_CLIENT_KEY").map(PathBuf::from),
+            accept_invalid_certs: parse("VINYLDNS_ACCEPT_INVALID_CERTS")?,
+            user_agent: e("VINYLDNS_USER_AGENT"),
+        })
+    }
+
+    /// These settings, with any unset ones taken from `fallback`
+    pub fn or(self, fallback: Profile) -> Profile {
+        Profile {
+            host: self.host.or(fallback.host),
+            access_key: self.access_key.or(fallback.access_key),
+            secret_key: self.secret_key.or(fallback.secret_key),
+            credential_command: self.credential_command.or(fallback.credential_command),
+            credentials_file: self.credentials_file.or(fallback.credentials_file),
+            region: self.region.or(fallback.region),
+            service: self.service.or(fallback.service),
+            timeout: self.timeout.or(fallback.timeout),
+            connect_timeout: self.connect_timeout.or(fallback.connect_timeout),
+            proxy: self.proxy.or(fallback.proxy),
+            ca_bundle: self.ca_bundle.or(fallback.ca_bundle),
+            client_cert: self.client_cert.or(fallback.client_cert),
+            client_key: self.client_key.or(fallback.client_key),
+            accept_invalid_certs: self.accept_invalid_certs.or(fallback.accept_invalid_certs),
+            user_agent: self.user_agent.or(fallback.user_agent),
+        }
+    }
+
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+
+    pub(crate) fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout.map(Duration::from_secs)
+    }
+}
+
+impl Config {
+    /// `$VINYLDNS_CONFIG`, or `vinyldns/config.toml` in `$XDG_CONFIG_HOME` or `~/.config`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("VINYLDNS_CONFIG") {
+            return Some(path.into());
+        }
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(dir.join("vinyldns").join("config.toml"))
+    }
+
+    /// The config at the default path, or no profiles at all if there is no file there
+    pub fn load() -> Result<Self> {
+        match Config::default_path() {
+            Some(path) if path.exists() => Config::from_file(&path),
+            _ => Ok(Default::default()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Read(path.display().to_string(), e))?;
+        Ok(
+            toml::from_str(&input)
+                .map_err(|e| ConfigError::Parse(path.display().to_string(), e))?,
+        )
+    }
+
+    /// Layer `flags` over `env` over the named profile, or the default profile if there is one
+    pub fn resolve(&self, name: Option<&str>, flags: Profile, env: Profile) -> Result<Profile> {
+        let profile = match name {
+            Some(name) => self.profiles.get(name).cloned().ok_or_else(|| {
+                let path = Config::default_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                ConfigError::UnknownProfile(name.to_string(), path)
+            })?,
+            None => self
+                .profiles
+                .get(DEFAULT_PROFILE)
+                .cloned()
+                .unwrap_or_default(),
+        };
+        Ok(flags.or(env).or(profile))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let config: Config = toml::from_str(
+            r#"
+            [default]
+            host = "http://localhost:9000"
+            access_key = "default-key"
+
+            [prod]
+            host = "https://vinyldns.example.com"
+            access_key = "prod-key"
+            secret_key = "prod-secret"
+            timeout = 30
+            "#,
+        )
+        .unwrap();
+
+        let profile = config
+            .resolve(None, Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(profile.host.as_deref(), Some("http://localhost:9000"));
+        assert_eq!(profile.secret_key, None);
+
+        let flags = Profile {
+            host: Some("http://flag".to_string()),
+            ..Default::default()
+        };
+        let env = Profile {
+            host: Some("http://env".to_string()),
+            access_key: Some("env-key".to_string()),
+            ..Default::default()
+        };
+        let profile = config.resolve(Some("prod"), flags, env).unwrap();
+        assert_eq!(profile.host.as_deref(), Some("http://flag"));
+        assert_eq!(profile.access_key.as_deref(), Some("env-key"));
+        assert_eq!(profile.secret_key, Some("prod-secret".into()));
+        assert_eq!(profile.timeout(), Some(Duration::from_secs(30)));
+
+        let err = config
+            .resolve(Some("staging"), Default::default(), Default::default())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("no profile named staging"));
+        assert!(toml::from_str::<Config>("[default]\nhots = \"typo\"\n").is_err());
+    }
+}
@@ -0,0 +1,348 @@
+//! Where a client gets the keys it signs requests with.
+//!
+//! A `CredentialProvider` is asked for credentials before every request, so providers backed by
+//! a secrets manager can hand out rotated keys to long running processes. Secret keys are held
+//! in a `Secret`, which is zeroed when dropped and never printed.
+
+#![allow(non_local_definitions)] // failure's derive
+
+use crate::client::ClientError::MissingEnvironmentVariable;
+use crate::config::Config;
+use failure::Fail;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use zeroize::Zeroizing;
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+/// A secret string, zeroed when dropped and redacted from `Debug`
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Secret(Zeroizing::new(secret.into()))
+    }
+
+    /// The secret itself, for signing with
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Secret::new(secret)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Secret::new(secret)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// An access key and the secret key it is signed with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: Secret,
+}
+
+impl Credentials {
+    pub fn new(access_key: &str, secret_key: &str) -> Self {
+        Credentials {
+            access_key: access_key.to_string(),
+            secret_key: Secret::new(secret_key),
+        }
+    }
+
+    /// Credentials as JSON, `{"access_key": "...", "secret_key": "..."}`, or the same keys in TOML
+    pub fn parse(input: &str, source: &str) -> Result<Self> {
+        let parsed = if input.trim_start().starts_with('{') {
+            serde_json::from_str(input).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(input).map_err(|e| e.to_string())
+        };
+        Ok(parsed.map_err(|e| CredentialError::Parse(source.to_string(), e))?)
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum CredentialError {
+    #[fail(display = "credential command `{}` failed: {}", _0, _1)]
+    Command(String, String),
+    #[fail(display = "failed to read credentials from {}: {}", _0, _1)]
+    Read(String, std::io::Error),
+    #[fail(display = "invalid credentials from {}: {}", _0, _1)]
+    Parse(String, String),
+    #[fail(display = "no credentials found: {}", _0)]
+    NotFound(String),
+}
+
+/// Hands out the credentials to sign the next request with
+pub trait CredentialProvider: fmt::Debug + Send + Sync {
+    fn credentials(&self) -> Result<Credentials>;
+}
+
+/// Fixed credentials
+impl CredentialProvider for Credentials {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(self.clone())
+    }
+}
+
+/// `VINYLDNS_ACCESS_KEY` and `VINYLDNS_SECRET_KEY`, read again for every request
+#[derive(Debug, Default, Clone)]
+pub struct EnvCredentials;
+
+impl CredentialProvider for EnvCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        fn e(n: &str) -> Result<String> {
+            Ok(std::env::var(n).map_err(|_| MissingEnvironmentVariable(n.to_string()))?)
+        }
+        Ok(Credentials {
+            access_key: e("VINYLDNS_ACCESS_KEY")?,
+            secret_key: e("VINYLDNS_SECRET_KEY")?.into(),
+        })
+    }
+}
+
+/// The keys of a profile in the config file, or the default profile without a name
+#[derive(Debug, Default, Clone)]
+pub struct ProfileCredentials {
+    pub profile: Option<String>,
+}
+
+impl CredentialProvider for ProfileCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let profile = Config::load()?.resolve(
+            self.profile.as_deref(),
+            Default::default(),
+            Default::default(),
+        )?;
+        match (profile.access_key, profile.secret_key) {
+            (Some(access_key), Some(secret_key)) => Ok(Credentials {
+                access_key,
+                secret_key,
+            }),
+            _ => Err(
+                CredentialError::NotFound("profile has no access_key and secret_key".into()).into(),
+            ),
+        }
+    }
+}
+
+/// Runs a command printing credentials (see `Credentials::parse`) on stdout, again once they
+/// are older than `refresh`
+#[derive(Debug)]
+pub struct CommandCredentials {
+    command: String,
+    refresh: Duration,
+    cached: Mutex<Option<(Instant, Credentials)>>,
+}
+
+impl CommandCredentials {
+    /// Run `command` with the shell
+    pub fn new(command: &str) -> Self {
+        CommandCredentials {
+            command: command.to_string(),
+            refresh: Duration::from_secs(15 * 60),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// How long to use the credentials before running the command again, 15 minutes by default
+    pub fn refresh(mut self, refresh: Duration) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    fn run(&self) -> Result<Credentials> {
+        let failed = |e: String| CredentialError::Command(self.command.clone(), e);
+        let output = if cfg!(windows) {
+            std::process::Command::new("cmd")
+                .args(["/C", &self.command])
+                .output()
+        } else {
+            std::process::Command::new("sh")
+                .args(["-c", &self.command])
+                .output()
+        }
+        .map_err(|e| failed(e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(failed(format!("{}: {}", output.status, stderr.trim())).into());
+        }
+        let stdout = Zeroizing::new(
+            String::from_utf8(output.stdout).map_err(|_| failed("output is not utf-8".into()))?,
+        );
+        Credentials::parse(&stdout, &format!("`{}`", self.command))
+    }
+}
+
+impl CredentialProvider for CommandCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let mut cached = self.cached.lock().unwrap();
+        match &*cached {
+            Some((at, credentials)) if at.elapsed() < self.refresh => Ok(credentials.clone()),
+            _ => {
+                let credentials = self.run()?;
+                *cached = Some((Instant::now(), credentials.clone()));
+                Ok(credentials)
+            }
+        }
+    }
+}
+
+/// Credentials (see `Credentials::parse`) in a file, read again whenever it is modified, so a
+/// secrets manager rotating the file is picked up without a restart
+#[derive(Debug)]
+pub struct FileCredentials {
+    path: PathBuf,
+    cached: Mutex<Option<(SystemTime, Credentials)>>,
+}
+
+impl FileCredentials {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCredentials {
+            path: path.into(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl CredentialProvider for FileCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let source = self.path.display().to_string();
+        let read_error = |e| CredentialError::Read(source.clone(), e);
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .map_err(read_error)?;
+        let mut cached = self.cached.lock().unwrap();
+        match &*cached {
+            Some((at, credentials)) if *at == modified => Ok(credentials.clone()),
+            _ => {
+                let input =
+                    Zeroizing::new(std::fs::read_to_string(&self.path).map_err(read_error)?);
+                let credentials = Credentials::parse(&input, &source)?;
+                *cached = Some((modified, credentials.clone()));
+                Ok(credentials)
+            }
+        }
+    }
+}
+
+/// Tries each provider in turn, using the first which has credentials
+#[derive(Debug, Default)]
+pub struct ChainCredentials {
+    providers: Vec<Box<dyn CredentialProvider>>,
+}
+
+impl ChainCredentials {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The environment, then the named profile
+    pub fn default_chain(profile: Option<&str>) -> Self {
+        ChainCredentials::new()
+            .with(EnvCredentials)
+            .with(ProfileCredentials {
+                profile: profile.map(String::from),
+            })
+    }
+
+    pub fn with(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+impl CredentialProvider for ChainCredentials {
+    fn credentials(&self) -> Result<Credentials> {
+        let mut errors = vec![];
+        for provider in &self.providers {
+            match provider.credentials() {
+                Ok(credentials) => return Ok(credentials),
+                Err(e) => errors.push(format!("{:?}: {}", provider, e)),
+            }
+        }
+        Err(CredentialError::NotFound(errors.join("; ")).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_secret_redacted() {
+        let credentials = Credentials::new("access", "hunter2");
+        assert_eq!(
+            format!("{:?}", credentials),
+            r#"Credentials { access_key: "access", secret_key: Secret(<redacted>) }"#
+        );
+        assert_eq!(credentials.secret_key.expose(), "hunter2");
+    }
+
+    #[test]
+    fn test_file_credentials() {
+        let path = std::env::temp_dir().join(format!("vinyldns-creds-{}", std::process::id()));
+        std::fs::write(&path, "access_key = \"a\"\nsecret_key = \"one\"\n").unwrap();
+        let provider = FileCredentials::new(&path);
+        assert_eq!(provider.credentials().unwrap().secret_key.expose(), "one");
+
+        // rotated, with a modification time that is certainly different
+        std::fs::write(&path, r#"{"access_key": "a", "secret_key": "two"}"#).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(provider.credentials().unwrap().secret_key.expose(), "two");
+        std::fs::remove_file(&path).unwrap();
+        assert!(provider.credentials().is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_command_credentials() {
+        let provider = CommandCredentials::new(r#"echo '{"access_key": "a", "secret_key": "b"}'"#);
+        assert_eq!(provider.credentials().unwrap(), Credentials::new("a", "b"));
+
+        let chain = ChainCredentials::new()
+            .with(CommandCredentials::new("echo nope >&2; exit 1"))
+            .with(Credentials::new("fallback", "secret"));
+        assert_eq!(chain.credentials().unwrap().access_key, "fallback");
+        let err = ChainCredentials::new()
+            .with(CommandCredentials::new("exit 3"))
+            .credentials()
+            .unwrap_err();
+        assert!(err.to_string().contains("exit status: 3"));
+    }
+}
@@ -0,0 +1,333 @@
+//! Semantic comparison of record sets.
+//!
+//! Record sets are matched by their name relative to the zone and their type, ignoring case,
+//! and compared only on their TTL and records, so fields the server fills in (`id`, `created`, `status`, ...) never
+//! show up as differences. Records are compared as sets, ignoring their order and the case and
+//! trailing dots of the domain names and hex strings in them.
+
+#![allow(non_local_definitions)] // failure's derive
+
+use crate::api_types::*;
+use crate::names;
+use crate::zone_file;
+use failure::Fail;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+#[derive(Debug, Fail)]
+pub enum DiffError {
+    #[fail(display = "record set {} {} is listed more than once", _0, _1)]
+    Duplicate(String, RecordType),
+}
+
+/// What it takes to turn one collection of record sets into another
+#[derive(Serialize, Debug, Default)]
+pub struct Diff {
+    /// record sets only in the new collection
+    pub added: Vec<RecordSet>,
+    /// record sets only in the old collection
+    pub removed: Vec<RecordSet>,
+    /// record sets in both, but with different TTLs or records
+    pub changed: Vec<RecordSetDiff>,
+}
+
+/// The differences between two versions of a record set
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordSetDiff {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_set_type: RecordType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<TtlChange>,
+    #[serde(serialize_with = "serialize_records")]
+    pub added: Vec<RecordData>,
+    #[serde(serialize_with = "serialize_records")]
+    pub removed: Vec<RecordData>,
+    /// the old record set, as given
+    #[serde(skip)]
+    pub old: RecordSet,
+    /// the new record set, as given
+    #[serde(skip)]
+    pub new: RecordSet,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TtlChange {
+    pub old: i32,
+    pub new: i32,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare the `old` record sets of the zone `origin` with the `new` ones, failing if either
+/// has the same record set twice
+pub fn diff(old: &[RecordSet], new: &[RecordSet], origin: &str) -> Result<Diff> {
+    let origin = names::absolute(origin);
+    let (old, new) = (by_key(old, &origin)?, by_key(new, &origin)?);
+
+    let mut diff = Diff::default();
+    for (key, old_rs) in &old {
+        match new.get(key) {
+            None => diff.removed.push((*old_rs).clone()),
+            Some(new_rs) => {
+                if let Some(changed) = diff_record_set(old_rs, new_rs) {
+                    diff.changed.push(changed);
+                }
+            }
+        }
+    }
+    diff.added = new
+        .iter()
+        .filter(|(key, _)| !old.contains_key(key))
+        .map(|(_, rs)| (*rs).clone())
+        .collect();
+    Ok(diff)
+}
+
+fn by_key<'a>(
+    record_sets: &'a [RecordSet],
+    origin: &str,
+) -> Result<BTreeMap<(String, RecordType), &'a RecordSet>> {
+    let mut keyed = BTreeMap::new();
+    for rs in record_sets {
+        let name = names::relative_name(&rs.name, origin).to_lowercase();
+        let typ = rs.record_set_type.clone();
+        if keyed.insert((name, typ.clone()), rs).is_some() {
+            return Err(DiffError::Duplicate(rs.name.clone(), typ).into());
+        }
+    }
+    Ok(keyed)
+}
+
+/// Compare two versions of the same record set, `None` if they are the same
+pub fn diff_record_set(old: &RecordSet, new: &RecordSet) -> Option<RecordSetDiff> {
+    let only_in = |a: &[RecordData], b: &[RecordData]| {
+        let b: Vec<_> = b.iter().map(normalize_record).collect();
+        let mut only = vec![];
+        for record in a {
+            let normalized = normalize_record(record);
+            if !b.contains(&normalized) && !only.iter().any(|r| normalize_record(r) == normalized) {
+                only.push(record.clone());
+            }
+        }
+        only
+    };
+    let added = only_in(&new.records, &old.records);
+    let removed = only_in(&old.records, &new.records);
+    let ttl = Some(TtlChange {
+        old: old.ttl,
+        new: new.ttl,
+    })
+    .filter(|ttl| ttl.old != ttl.new);
+    if ttl.is_none() && added.is_empty() && removed.is_empty() {
+        return None;
+    }
+    Some(RecordSetDiff {
+        name: new.name.clone(),
+        record_set_type: new.record_set_type.clone(),
+        ttl,
+        added,
+        removed,
+        old: old.clone(),
+        new: new.clone(),
+    })
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+/// Domain names and hex strings in a record compare regardless of case and trailing dots
+fn normalize_record(record: &RecordData) -> RecordData {
+    use RecordData::*;
+    let mut record = record.clone();
+    match &mut record {
+        CNAME { cname: name }
+        | MX { exchange: name, .. }
+        | NS { nsdname: name }
+        | PTR { ptrdname: name }
+        | SRV { target: name, .. }
+        | NAPTR {
+            replacement: name, ..
+        } => *name = normalize_name(name),
+        SOA { mname, rname, .. } => {
+            *mname = normalize_name(mname);
+            *rname = normalize_name(rname);
+        }
+        DS { digest: hex, .. }
+        | SSHFP {
+            fingerprint: hex, ..
+        } => *hex = hex.to_lowercase(),
+        _ => {}
+    }
+    record
+}
+
+fn serialize_records<S: Serializer>(
+    records: &[RecordData],
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(records.iter().cloned().map(Record::from))
+}
+
+fn rdata(record: &RecordData) -> String {
+    zone_file::rdata(record).unwrap_or_else(|| format!("{:?}", record))
+}
+
+/// `+` followed by the record set and each of its records
+fn fmt_added(f: &mut fmt::Formatter<'_>, rs: &RecordSet) -> fmt::Result {
+    writeln!(f, "+ {} {} ttl {}", rs.name, rs.record_set_type, rs.ttl)?;
+    for record in &rs.records {
+        writeln!(f, "    + {}", rdata(record))?;
+    }
+    Ok(())
+}
+
+/// `-` followed by the record set and each of its records
+fn fmt_removed(f: &mut fmt::Formatter<'_>, rs: &RecordSet) -> fmt::Result {
+    writeln!(f, "- {} {}", rs.name, rs.record_set_type)?;
+    for record in &rs.records {
+        writeln!(f, "    - {}", rdata(record))?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for RecordSetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "~ {} {}", self.name, self.record_set_type)?;
+        if let Some(ttl) = self.ttl {
+            writeln!(f, "    ttl {} -> {}", ttl.old, ttl.new)?;
+        }
+        for record in &self.removed {
+            writeln!(f, "    - {}", rdata(record))?;
+        }
+        for record in &self.added {
+            writeln!(f, "    + {}", rdata(record))?;
+        }
+        Ok(())
+    }
+}
+
+/// Removed record sets, then changed ones, then added ones, each line ending in a newline
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rs in &self.removed {
+            fmt_removed(f, rs)?;
+        }
+        for changed in &self.changed {
+            write!(f, "{}", changed)?;
+        }
+        for rs in &self.added {
+            fmt_added(f, rs)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cname(name: &str, ttl: i32, cnames: &[&str]) -> RecordSet {
+        RecordSet::new(
+            name,
+            ttl,
+            cnames
+                .iter()
+                .map(|cname| RecordData::CNAME {
+                    cname: cname.to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut server = cname("WWW.ok.", 300, &["Host.OK."]);
+        server.id = "1".to_string();
+        server.created = "2019-01-01T00:00:00Z".to_string();
+        server.status = Some(RecordSetStatus::Active);
+        let old = vec![
+            server,
+            cname("ttl", 300, &["a."]),
+            cname("old", 300, &["a."]),
+        ];
+        let new = vec![
+            cname("www", 300, &["host.ok"]),
+            cname("ttl", 600, &["b."]),
+            cname("new", 300, &["a."]),
+        ];
+        let diff = diff(&old, &new, "ok").unwrap();
+        assert_eq!(diff.removed[0].name, "old");
+        assert_eq!(diff.added[0].name, "new");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.to_string(),
+            "- old CNAME\n    - a.\n~ ttl CNAME\n    ttl 300 -> 600\n    - a.\n    + b.\n+ new CNAME ttl 300\n    + a.\n"
+        );
+        assert_eq!(
+            serde_json::to_value(&diff.changed).unwrap(),
+            serde_json::json!([{
+                "name": "ttl",
+                "type": "CNAME",
+                "ttl": { "old": 300, "new": 600 },
+                "added": [{ "cname": "b." }],
+                "removed": [{ "cname": "a." }],
+            }])
+        );
+    }
+
+    #[test]
+    fn test_diff_names() {
+        let old = vec![cname("@", 300, &["a."]), cname("www.ok.", 300, &["a."])];
+        let new = vec![cname("OK.", 300, &["a."]), cname("WWW", 300, &["a."])];
+        assert!(diff(&old, &new, "ok.").unwrap().is_empty());
+
+        // names without a trailing dot are relative, apart from the zone name itself
+        let new = vec![cname("ok", 300, &["a."]), cname("www.ok", 300, &["a."])];
+        let changes = diff(&old, &new, "ok.").unwrap();
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.added[0].name, "www.ok");
+
+        let new = vec![cname("www", 300, &["a."]), cname("www.ok.", 600, &["a."])];
+        let err = diff(&old, &new, "ok.").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "record set www.ok. CNAME is listed more than once"
+        );
+    }
+
+    #[test]
+    fn test_diff_record_order() {
+        let old = RecordSet::new(
+            "mx",
+            300,
+            vec![
+                RecordData::MX {
+                    preference: 10,
+                    exchange: "a.ok.".to_string(),
+                },
+                RecordData::MX {
+                    preference: 20,
+                    exchange: "b.ok.".to_string(),
+                },
+            ],
+        );
+        let mut new = old.clone();
+        new.records.reverse();
+        assert!(diff_record_set(&old, &new).is_none());
+        new.records.pop();
+        let changed = diff_record_set(&old, &new).unwrap();
+        assert!(changed.ttl.is_none());
+        assert!(changed.added.is_empty());
+        assert_eq!(changed.removed, vec![old.records[0].clone()]);
+    }
+}
@@ -0,0 +1,31 @@
+//! A client for the [vinyldns](http://vinyldns.io/) api.
+//!
+//! `client::Client` makes blocking requests and `async_client::AsyncClient` async ones; both
+//! implement `client::VinylDns` and are configured by `client::ClientBuilder`. The api's request and response types are in `api_types`, and
+//! `aws_sign` signs requests the way the api expects. `client` also re-exports what configures a
+//! client: its `Transport`, `ListOptions` and `Profile`.
+//!
+//! ```no_run
+//! use vinyldns_rs::client::{Client, VinylDns};
+//!
+//! let client = Client::new("accessKey", "secretKey", "http://localhost:9000");
+//! for zone in client.zones_iter(&Default::default()) {
+//!     println!("{}", zone.unwrap().name);
+//! }
+//! ```
+//!
+//! The `cli` feature, on by default, only builds the `vinyldns_rs` binary; library users can
+//! leave it out with `default-features = false` to skip clap and env_logger.
+
+mod api;
+pub mod api_types;
+pub mod async_client;
+pub mod aws_sign;
+pub mod client;
+mod config;
+pub mod credentials;
+/// An in-process fake of the api, for tests
+#[cfg(any(test, feature = "mock-server"))]
+pub mod mock_server;
+pub mod names;
+mod transport;
@@ -1,85 +1,298 @@
-#![allow(dead_code, unused_imports)]
-
 use clap::{clap_app, crate_version, SubCommand};
-use log::{debug, info};
-use reqwest;
+#[cfg(test)]
+use vinyldns_rs::aws_sign;
+use vinyldns_rs::client::VinylDns;
+use vinyldns_rs::{api_types, client, names};
 
-mod api_types;
-mod aws_sign;
-mod client;
+mod diff;
+#[cfg(test)]
+#[path = "mock_server.rs"]
+#[allow(dead_code)] // some of it is only for the library's tests
+mod mock_server;
+mod plan;
+mod zone_file;
 
-fn aws_signv4(req: &mut reqwest::Request) {
-    use chrono::{Datelike, Timelike};
-    use reqwest::header;
-    use reqwest::header::HeaderValue;
-    use sha2::{Digest, Sha256};
+fn f<O: serde::Serialize>(x: Result<O, failure::Error>) -> Result<String, failure::Error> {
+    x.map(|x| serde_json::to_string_pretty(&x).unwrap())
+}
 
-    let ctime = aws_sign::Utc::now();
-    let payload = "".as_bytes();
+/// Distinct exit codes so scripts can tell how a command failed
+fn exit_code(e: &failure::Error) -> i32 {
+    use client::ClientError::*;
+    // eg. a failed import wraps the error of the request which failed
+    match e
+        .iter_chain()
+        .find_map(|e| e.downcast_ref::<client::ClientError>())
     {
-        let fallback_host = req.url().domain().unwrap().to_owned();
-        let fallback_content_type = "application/x-www-form-urlencoded; charset=UTF-8";
-        let fallback_date = &format!(
-            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
-            ctime.year(),
-            ctime.month(),
-            ctime.day(),
-            ctime.hour(),
-            ctime.minute(),
-            ctime.second()
-        );
+        Some(BadRequest(_)) | Some(BatchValidation(..)) => 3,
+        Some(Unauthorized(_)) | Some(Forbidden(_)) => 4,
+        Some(NotFound(_)) | Some(UnknownZone(_)) | Some(UnknownGroup(_)) => 5,
+        Some(Conflict(_)) => 6,
+        Some(ServerError(_)) | Some(Http(_)) => 7,
+        Some(Transport(_)) => 8,
+        Some(ChangeFailed(..)) | Some(Timeout(_)) => 9,
+        _ => 1,
+    }
+}
 
-        let headers = req.headers_mut();
-        if !headers.contains_key(header::HOST) {
-            headers.insert(header::HOST, fallback_host.parse().unwrap());
-        }
+fn g(m: &clap::ArgMatches<'_>, v: &str) -> String {
+    m.value_of(v).unwrap_or_default().into()
+}
+
+/// The zone given by `--zone` as a name or id, or else by its id in `id_arg`
+fn zone(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+    id_arg: &str,
+) -> Result<api_types::Zone, failure::Error> {
+    match m.value_of("zone") {
+        Some(zone) => client.zone_by_name_or_id(zone),
+        None => client.zone(&g(m, id_arg)),
+    }
+}
+
+/// Like `zone`, without looking the zone up when it is given by id
+fn zone_id(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+    id_arg: &str,
+) -> Result<String, failure::Error> {
+    match m.value_of("zone") {
+        Some(zone) => Ok(client.zone_by_name_or_id(zone)?.id),
+        None => Ok(g(m, id_arg)),
+    }
+}
+
+fn wait_for_zone(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+    update: Result<api_types::ZoneUpdateResponse, failure::Error>,
+) -> Result<String, failure::Error> {
+    if m.is_present("wait") {
+        f(update.and_then(|update| client.wait_for_zone_update(&update, &Default::default())))
+    } else {
+        f(update)
+    }
+}
+
+fn import_zone(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+) -> Result<String, failure::Error> {
+    let zone = zone(client, m, "id")?;
+    let record_sets = zone_file::parse_file(std::path::Path::new(&g(m, "file")), &zone.name)?;
+    if m.is_present("dry-run") {
+        return f(Ok(record_sets
+            .into_iter()
+            .filter(|rs| zone_file::importable(rs, &zone.name))
+            .collect::<Vec<_>>()));
+    }
+    let updates = zone_file::import_zone(client, &zone, &record_sets)?;
+    if m.is_present("wait") {
+        f(updates
+            .iter()
+            .map(|update| client.wait_for_record_set_update(update, &Default::default()))
+            .collect::<Result<Vec<_>, _>>())
+    } else {
+        f(Ok(updates))
+    }
+}
+
+fn diff_zone(client: &client::Client, m: &clap::ArgMatches<'_>) -> Result<String, failure::Error> {
+    let zone = zone(client, m, "id")?;
+    let mut current = client.record_sets(&zone.id)?;
+    current.retain(|rs| zone_file::importable(rs, &zone.name));
+    let mut file = zone_file::parse_file(std::path::Path::new(&g(m, "file")), &zone.name)?;
+    file.retain(|rs| zone_file::importable(rs, &zone.name));
+    let diff = diff::diff(&current, &file, &zone.name)?;
+    if m.is_present("json") {
+        f(Ok(diff))
+    } else if diff.is_empty() {
+        Ok("no differences".to_string())
+    } else {
+        Ok(diff.to_string().trim_end().to_string())
+    }
+}
 
-        if !headers.contains_key(header::CONTENT_TYPE) {
-            headers.insert(header::CONTENT_TYPE, fallback_content_type.parse().unwrap());
+/// The ACL rule described by the flags of `acl add` or `acl remove`
+fn acl_rule(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+) -> Result<api_types::ACLRule, failure::Error> {
+    let access_level = g(m, "access").parse()?;
+    let mut rule = match (m.value_of("user"), m.value_of("group")) {
+        (Some(user), _) => api_types::ACLRule::for_user(access_level, &client.user(user)?.id),
+        (_, Some(group)) => {
+            api_types::ACLRule::for_group(access_level, &client.group_by_name_or_id(group)?.id)
         }
+        (None, None) => api_types::ACLRule::new(access_level),
+    };
+    rule.description = m.value_of("description").map(String::from);
+    rule.record_mask = m.value_of("record-mask").map(String::from);
+    rule.record_types = m
+        .values_of("record-types")
+        .map(|types| types.map(|t| t.parse()).collect::<Result<_, _>>())
+        .transpose()?
+        .unwrap_or_default();
+    Ok(rule)
+}
 
-        headers.insert("X-Amz-Date", fallback_date.parse().unwrap());
-        headers.insert(
-            "X-Amz-Content-Sha256",
-            HeaderValue::from_str(&format!("{:x}", Sha256::digest(payload))).unwrap(),
-        );
+fn acl(client: &client::Client, m: &clap::ArgMatches<'_>) -> Result<String, failure::Error> {
+    let (add, m) = match m.subcommand() {
+        ("list", Some(m)) => return f(zone(client, m, "id").map(|zone| zone.acl.rules)),
+        ("add", Some(m)) => (true, m),
+        ("remove", Some(m)) => (false, m),
+        _ => unreachable!("clap requires a subcommand"),
+    };
+    let zone_id = zone_id(client, m, "id")?;
+    let rule = acl_rule(client, m)?;
+    let mut change = if add {
+        client.acl_rule_add(&zone_id, &rule)?
+    } else {
+        client.acl_rule_delete(&zone_id, &rule)?
+    };
+    if m.is_present("wait") {
+        change = client.wait_for_zone_change(&zone_id, &change.id, &Default::default())?;
+    }
+    f(Ok(change.zone.acl.rules))
+}
+
+/// `acl add` or `acl remove`, which take the same flags
+fn acl_rule_command(name: &'static str, about: &'static str) -> clap::App<'static, 'static> {
+    clap_app! { @app (SubCommand::with_name(name))
+        (about: about)
+        (@arg id: -i --id +takes_value required_unless[zone] "")
+        (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+        (@arg access: -a --access * +takes_value possible_value[NoAccess Read Write Delete] "")
+        (@arg user: -u --user +takes_value conflicts_with[group] "user name or id the rule is for")
+        (@arg group: -g --group +takes_value "group name or id the rule is for, without --user or --group it is for everyone")
+        (@arg ("record-mask"): -m --("record-mask") +takes_value "regex of the record names the rule is for")
+        (@arg ("record-types"): -t --("record-types") +takes_value +use_delimiter "comma separated record types the rule is for")
+        (@arg description: -d --description +takes_value "")
+        (@arg wait: -w --wait "wait for the change to complete")
+    }
+}
+
+/// How long ago an api timestamp was, roughly
+fn ago(time: &str) -> Option<String> {
+    let time = chrono::DateTime::parse_from_rfc3339(time).ok()?;
+    let seconds = (chrono::Utc::now() - time.with_timezone(&chrono::Utc)).num_seconds();
+    let (n, unit) = match seconds {
+        s if s < 60 => return Some("just now".to_string()),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 24 * 60 * 60 => (s / (60 * 60), "hour"),
+        s => (s / (24 * 60 * 60), "day"),
+    };
+    Some(format!(
+        "{} {}{} ago",
+        n,
+        unit,
+        if n == 1 { "" } else { "s" }
+    ))
+}
+
+fn sync_zone(client: &client::Client, m: &clap::ArgMatches<'_>) -> Result<String, failure::Error> {
+    let zone = zone(client, m, "id")?;
+    let in_progress = client.zone_sync_in_progress(&zone.id)?;
+    let already_syncing = in_progress.is_some();
+    let mut sync = match in_progress {
+        Some(sync) => sync,
+        None => client.zone_sync(&zone.id)?,
+    };
+    if m.is_present("wait") {
+        sync = client.wait_for_zone_sync(&sync, &Default::default())?;
+    }
+    if m.is_present("json") {
+        return f(Ok(sync));
     }
+    let last_sync = match ago(&zone.latest_sync) {
+        Some(ago) => format!("{} was last synced {}", zone.name, ago),
+        None => format!("{} has not been synced", zone.name),
+    };
+    let status = match (already_syncing, m.is_present("wait")) {
+        (_, true) => format!("sync {} is {}", sync.id, sync.status),
+        (true, false) => format!("sync {} is already in progress", sync.id),
+        (false, false) => format!("started sync {}", sync.id),
+    };
+    Ok(format!("{}\n{}", last_sync, status))
+}
 
-    let auth_val = aws_sign::auth_header(
-        req.method(),
-        req.url(),
-        req.headers(),
-        payload,
-        ctime,
-        "us-east-1",
-        "s3",
-        "testUserAccessKey",
-        "testUserSecretKey",
-    );
-    req.headers_mut()
-        .insert(header::AUTHORIZATION, auth_val.parse().unwrap());
+fn plan_zone(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+) -> Result<(api_types::Zone, plan::Plan), failure::Error> {
+    let mut desired = plan::DesiredState::from_file(std::path::Path::new(&g(m, "file")))?;
+    let zone = if m.is_present("zone") || m.is_present("zone-id") {
+        zone(client, m, "zone-id")?
+    } else {
+        client.zone_by_name_or_id(&desired.zone)?
+    };
+    desired.zone = zone.name.clone();
+    let plan = plan::plan(&desired, &client.record_sets(&zone.id)?)?;
+    Ok((zone, plan))
 }
 
-fn f<O: serde::Serialize>(x: Result<O, failure::Error>) -> String {
-    match x {
-        Ok(x) => format!("{}", serde_json::to_string_pretty(&x).unwrap()),
-        Err(x) => format!("{}", x),
+fn apply(client: &client::Client, m: &clap::ArgMatches<'_>) -> Result<String, failure::Error> {
+    let (zone, plan) = plan_zone(client, m)?;
+    if plan.is_empty() {
+        return Ok(plan.to_string());
+    }
+    if m.is_present("batch") {
+        return f(plan::apply_batch(client, &zone, &plan));
     }
+    let wait = Default::default();
+    plan::apply(
+        client,
+        &zone,
+        &plan,
+        Some(&wait).filter(|_| m.is_present("wait")),
+    )?;
+    Ok(plan.to_string())
 }
 
-fn g(m: &clap::ArgMatches<'_>, v: &str) -> String {
-    m.value_of(v).unwrap_or_default().into()
+fn list_options(m: &clap::ArgMatches<'_>) -> client::ListOptions {
+    let mut options = client::ListOptions::new().ignore_access(m.is_present("ignore-access"));
+    if let Some(name_filter) = m.value_of("name-filter") {
+        options = options.name_filter(name_filter);
+    }
+    options
 }
 
-fn main() {
-    env_logger::init();
+/// `list_options` with the record set filters of `--name`, `--type`, `--owner-group` and `--sort`
+fn record_set_options(
+    client: &client::Client,
+    m: &clap::ArgMatches<'_>,
+) -> Result<client::ListOptions, failure::Error> {
+    let mut options = list_options(m);
+    if let Some(name) = m.value_of("name") {
+        options = options.name_filter(name);
+    }
+    for record_type in m.values_of("type").into_iter().flatten() {
+        options = options.record_type_filter(record_type.parse()?);
+    }
+    if let Some(group) = m.value_of("owner-group") {
+        options = options.record_owner_group_filter(&client.group_by_name_or_id(group)?.id);
+    }
+    if let Some(sort) = m.value_of("sort") {
+        options = options.name_sort(sort.parse()?);
+    }
+    Ok(options)
+}
 
-    let matches = clap_app!(("vinyldns-client") =>
+fn app() -> clap::App<'static, 'static> {
+    clap_app!(("vinyldns-client") =>
         (@setting SubcommandRequiredElseHelp)
         (version: crate_version!())
+        (@arg profile: --profile +takes_value +global "profile of the config file to use, see README")
+        (@arg host: --host +takes_value +global "VinylDNS api url, overriding VINYLDNS_HOST and the profile")
+        (@arg region: --region +takes_value +global "region to sign requests for, overriding VINYLDNS_REGION and the profile")
+        (@arg service: --service +takes_value +global "service name to sign requests for, overriding VINYLDNS_SERVICE and the profile")
         // HACK: currently, (@subcommand name-with-hyphen => ...) or (@subcommand ("name-with-hyphen") => ...) won't parse
         // https://github.com/clap-rs/clap/pull/1523
-        (subcommand: SubCommand::with_name("list-groups").alias("lg"))
+        (subcommand: clap_app!{ @app (SubCommand::with_name("list-groups"))
+            (alias: "lg")
+            (@arg ("name-filter"): -f --("name-filter") +takes_value "only list groups whose name contains this")
+        })
         (subcommand: clap_app!{ @app (SubCommand::with_name("create-group"))
             (alias: "cg")
             (@arg name: -n --name * +takes_value "")
@@ -90,24 +303,111 @@ fn main() {
             (alias: "dg")
             (@arg id: -i --id * +takes_value "")
         })
-        (subcommand: SubCommand::with_name("list-zones").alias("lz"))
+        (subcommand: clap_app!{ @app (SubCommand::with_name("list-zones"))
+            (alias: "lz")
+            (@arg ("name-filter"): -f --("name-filter") +takes_value "only list zones whose name contains this")
+            (@arg ("ignore-access"): --("ignore-access") "list all zones, not just those you have access to")
+        })
         (subcommand: clap_app!{ @app (SubCommand::with_name("create-zone"))
             (alias: "cz")
             (@arg name: -n --name * +takes_value "")
             (@arg email: -e --email * +takes_value "")
             (@arg ("admin-group-id"): -a --("admin-group-id") * +takes_value "")
+            (@arg wait: -w --wait "wait for the change to complete")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("delete-zone"))
+            (alias: "dz")
+            (@arg id: -i --id +takes_value required_unless[zone] "")
+            (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+            (@arg wait: -w --wait "wait for the change to complete")
         })
         (subcommand: clap_app!{ @app (SubCommand::with_name("get-record-sets"))
             (alias: "gr")
+            (@arg id: -i --id +takes_value required_unless[zone] "")
+            (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+            (@arg name: -n --name +takes_value "only record sets whose name contains this, * matches anything")
+            (@arg type: -t --type +takes_value +use_delimiter "comma separated record types to list")
+            (@arg ("owner-group"): -o --("owner-group") +takes_value "only record sets owned by this group name or id")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("delete-record-set"))
+            (alias: "dr")
+            (@arg ("zone-id"): -z --("zone-id") +takes_value required_unless[zone] conflicts_with[zone] "")
+            (@arg zone: --zone +takes_value "zone name or id, instead of --zone-id")
             (@arg id: -i --id * +takes_value "")
+            (@arg wait: -w --wait "wait for the change to complete")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("sync-zone"))
+            (alias: "sz")
+            (about: "sync a zone's record sets with its dns backend, unless a sync is already in progress")
+            (@arg id: -i --id +takes_value required_unless[zone] "")
+            (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+            (@arg wait: -w --wait "wait for the sync to complete")
+            (@arg json: -j --json "print the sync's zone change as json")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("search-records"))
+            (alias: "sr")
+            (about: "search the record sets of every zone by name")
+            (@arg name: -n --name * +takes_value "record name, or fully qualified name, to search for, * matches anything")
+            (@arg type: -t --type +takes_value +use_delimiter "comma separated record types to search for")
+            (@arg ("owner-group"): -o --("owner-group") +takes_value "only record sets owned by this group name or id")
+            (@arg sort: --sort +takes_value possible_value[ASC DESC] "order by name")
+        })
+        (@subcommand acl =>
+            (about: "list, add or remove a zone's ACL rules, printing the resulting rules")
+            (@setting SubcommandRequiredElseHelp)
+            (@subcommand list =>
+                (@arg id: -i --id +takes_value required_unless[zone] "")
+                (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+            )
+            (subcommand: acl_rule_command("add", "add a rule to a zone's ACL"))
+            (subcommand: acl_rule_command("remove", "remove a rule, with exactly the same flags it was added with"))
+        )
+        (subcommand: clap_app!{ @app (SubCommand::with_name("export-zone"))
+            (alias: "ez")
+            (about: "print a zone's record sets as a BIND style zone file")
+            (@arg id: -i --id +takes_value required_unless[zone] "")
+            (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("import-zone"))
+            (alias: "iz")
+            (about: "create the record sets of a BIND style zone file in an existing zone")
+            (@arg id: -i --id +takes_value required_unless[zone] "")
+            (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+            (@arg file: -f --file * +takes_value "zone file to import")
+            (@arg ("dry-run"): -n --("dry-run") "only print the record sets which would be created")
+            (@arg wait: -w --wait "wait for the changes to complete")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("diff-zone"))
+            (alias: "df")
+            (about: "compare a zone's record sets with a BIND style zone file")
+            (@arg id: -i --id +takes_value required_unless[zone] "")
+            (@arg zone: --zone +takes_value conflicts_with[id] "zone name or id, instead of --id")
+            (@arg file: -f --file * +takes_value "zone file to compare with")
+            (@arg json: -j --json "print the diff as json")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("plan"))
+            (about: "print the changes which would bring a zone's record sets to a desired state")
+            (@arg file: -f --file * +takes_value "desired state as .yaml, .json or .toml")
+            (@arg ("zone-id"): -z --("zone-id") +takes_value conflicts_with[zone] "zone to plan, instead of looking it up by name")
+            (@arg zone: --zone +takes_value "zone name or id to plan, instead of the one in the file")
+            (@arg json: -j --json "print the changes as json")
+        })
+        (subcommand: clap_app!{ @app (SubCommand::with_name("apply"))
+            (about: "bring a zone's record sets to a desired state")
+            (@arg file: -f --file * +takes_value "desired state as .yaml, .json or .toml")
+            (@arg ("zone-id"): -z --("zone-id") +takes_value conflicts_with[zone] "zone to apply to, instead of looking it up by name")
+            (@arg zone: --zone +takes_value "zone name or id to apply to, instead of the one in the file")
+            (@arg batch: -b --batch "make the changes as a single batch change")
+            (@arg wait: -w --wait "wait for each change to complete")
         })
     )
-    .get_matches();
-
-    let client = client::Client::from_env().unwrap();
+}
 
-    let out = match matches.subcommand() {
-        ("list-groups", _) => f(client.groups()),
+fn run(client: &client::Client, matches: &clap::ArgMatches<'_>) -> Result<String, failure::Error> {
+    match matches.subcommand() {
+        ("list-groups", Some(matches)) => f(client
+            .groups_iter(&list_options(matches))
+            .collect::<Result<Vec<_>, _>>()),
         ("create-group", Some(matches)) => f(client.group_create(&api_types::Group {
             name: g(matches, "name"),
             email: g(matches, "email"),
@@ -115,17 +415,554 @@ fn main() {
             ..std::default::Default::default()
         })),
         ("delete-group", Some(matches)) => f(client.group_delete(&g(matches, "id"))),
-        ("list-zones", _) => f(client.zones()),
-        ("create-zone", Some(matches)) => f(client.zone_create(&api_types::Zone {
-            name: g(matches, "name"),
-            email: g(matches, "email"),
-            admin_group_id: g(matches, "admin-group-id"),
-            is_test: true,
-            ..std::default::Default::default()
-        })),
-        ("get-record-sets", Some(matches)) => f(client.record_sets(&g(matches, "id"))),
+        ("list-zones", Some(matches)) => f(client
+            .zones_iter(&list_options(matches))
+            .collect::<Result<Vec<_>, _>>()),
+        ("create-zone", Some(matches)) => {
+            let update = client.zone_create(&api_types::Zone {
+                name: g(matches, "name"),
+                email: g(matches, "email"),
+                admin_group_id: g(matches, "admin-group-id"),
+                is_test: true,
+                ..std::default::Default::default()
+            });
+            wait_for_zone(client, matches, update)
+        }
+        ("delete-zone", Some(matches)) => {
+            let update = zone_id(client, matches, "id").and_then(|id| client.zone_delete(&id));
+            wait_for_zone(client, matches, update)
+        }
+        ("get-record-sets", Some(matches)) => {
+            let options = record_set_options(client, matches)?;
+            f(zone_id(client, matches, "id")
+                .and_then(|id| client.record_sets_filtered(&id, &options)))
+        }
+        ("delete-record-set", Some(matches)) => {
+            let update = zone_id(client, matches, "zone-id")
+                .and_then(|zone_id| client.record_set_delete(&zone_id, &g(matches, "id")));
+            if matches.is_present("wait") {
+                f(update.and_then(|update| {
+                    client.wait_for_record_set_update(&update, &Default::default())
+                }))
+            } else {
+                f(update)
+            }
+        }
+        ("search-records", Some(matches)) => {
+            f(record_set_options(client, matches).and_then(|options| {
+                client
+                    .record_sets_search_iter(&options)
+                    .collect::<Result<Vec<_>, _>>()
+            }))
+        }
+        ("sync-zone", Some(matches)) => sync_zone(client, matches),
+        ("acl", Some(matches)) => acl(client, matches),
+        ("export-zone", Some(matches)) => {
+            zone_file::export_zone(client, &zone_id(client, matches, "id")?)
+        }
+        ("import-zone", Some(matches)) => import_zone(client, matches),
+        ("diff-zone", Some(matches)) => diff_zone(client, matches),
+        ("plan", Some(matches)) => {
+            let (_, plan) = plan_zone(client, matches)?;
+            if matches.is_present("json") {
+                f(Ok(plan.diff))
+            } else {
+                Ok(plan.to_string())
+            }
+        }
+        ("apply", Some(matches)) => apply(client, matches),
         _ => unimplemented!(),
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let matches = app().get_matches();
+    let flags = client::Profile {
+        host: matches.value_of("host").map(String::from),
+        region: matches.value_of("region").map(String::from),
+        service: matches.value_of("service").map(String::from),
+        ..Default::default()
     };
+    let client = match client::Client::from_config(matches.value_of("profile"), flags) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exit_code(&e));
+        }
+    };
+
+    match run(&client, &matches) {
+        Ok(out) => println!("{}", out),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exit_code(&e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_server::MockServer;
+
+    fn cli(client: &client::Client, args: &[&str]) -> Result<serde_json::Value, failure::Error> {
+        let matches =
+            app().get_matches_from(std::iter::once("vinyldns").chain(args.iter().cloned()));
+        run(client, &matches).map(|out| serde_json::from_str(&out).unwrap())
+    }
+
+    /// Mock server with zone `ok.` administered by group `ok-group`
+    fn setup() -> (MockServer, client::Client, api_types::Zone) {
+        let server = MockServer::start();
+        let client = server.client();
+        let group = client
+            .group_create(&api_types::Group {
+                name: "ok-group".to_string(),
+                email: "test@example.com".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let update = client
+            .zone_create(&api_types::Zone {
+                name: "ok.".to_string(),
+                email: "test@example.com".to_string(),
+                admin_group_id: group.id,
+                ..Default::default()
+            })
+            .unwrap();
+        let zone = client
+            .wait_for_zone_update(&update, &Default::default())
+            .unwrap()
+            .zone;
+        (server, client, zone)
+    }
+
+    /// Creates `rs` in `zone` and waits for it to be applied
+    fn create_record_set(
+        client: &client::Client,
+        zone: &api_types::Zone,
+        rs: &api_types::RecordSet,
+    ) -> api_types::RecordSet {
+        let update = client.record_set_create(&zone.id, rs).unwrap();
+        client
+            .wait_for_record_set_update(&update, &Default::default())
+            .unwrap()
+            .record_set
+    }
+
+    fn a(name: &str, ttl: i32) -> api_types::RecordSet {
+        api_types::RecordSet::new(
+            name,
+            ttl,
+            vec![api_types::RecordData::A {
+                address: "10.0.0.1".parse().unwrap(),
+            }],
+        )
+    }
+
+    /// Writes `contents` to a file in the temp dir, returning its path
+    fn temp_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("vinyldns-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_cli_groups() {
+        let (_server, client, zone) = setup();
+
+        let group = cli(
+            &client,
+            &[
+                "create-group",
+                "--name",
+                "other-group",
+                "--email",
+                "test@example.com",
+            ],
+        )
+        .unwrap();
+        let group_id = group["id"].as_str().unwrap();
+        let groups = cli(&client, &["list-groups", "--name-filter", "other"]).unwrap();
+        assert_eq!(groups[0]["name"], "other-group");
+        assert!(groups[1].is_null());
+
+        // still administers ok.
+        let err = cli(&client, &["delete-group", "--id", &zone.admin_group_id]).unwrap_err();
+        assert_eq!(exit_code(&err), 3);
+        cli(&client, &["delete-group", "--id", group_id]).unwrap();
+        let err = cli(&client, &["delete-group", "--id", group_id]).unwrap_err();
+        assert_eq!(exit_code(&err), 5);
+    }
+
+    #[test]
+    fn test_cli_zones() {
+        let (_server, client, zone) = setup();
+
+        let change = cli(
+            &client,
+            &[
+                "create-zone",
+                "--name",
+                "other.",
+                "--email",
+                "test@example.com",
+                "--admin-group-id",
+                &zone.admin_group_id,
+                "--wait",
+            ],
+        )
+        .unwrap();
+        assert_eq!(change["status"], "Synced");
+        let zones = cli(&client, &["list-zones"]).unwrap();
+        assert_eq!(zones.as_array().unwrap().len(), 2);
+
+        let err = cli(&client, &["get-record-sets", "--zone", "nope."]).unwrap_err();
+        assert_eq!(err.to_string(), "no zone with name or id nope.");
+        assert_eq!(exit_code(&err), 5);
+        // the deleted zone's changes 404 once it is gone
+        let change = cli(&client, &["delete-zone", "--zone", "ok.", "--wait"]).unwrap();
+        assert_eq!(change["status"], "Synced");
+        assert_eq!(change["zone"]["status"], "Deleted");
+        let zones = cli(&client, &["list-zones"]).unwrap();
+        assert_eq!(zones[0]["name"], "other.");
+        assert!(zones[1].is_null());
+    }
+
+    #[test]
+    fn test_cli_record_sets() {
+        let (_server, client, zone) = setup();
+        let rs = create_record_set(
+            &client,
+            &zone,
+            &api_types::RecordSet::new(
+                "www",
+                300,
+                vec![api_types::RecordData::CNAME {
+                    cname: "ok.".to_string(),
+                }],
+            ),
+        );
+
+        let record_sets = cli(&client, &["get-record-sets", "--id", &zone.id]).unwrap();
+        assert_eq!(record_sets[0]["records"][0]["cname"], "ok.");
+        let change = cli(
+            &client,
+            &[
+                "delete-record-set",
+                "--zone-id",
+                &zone.id,
+                "--id",
+                &rs.id,
+                "--wait",
+            ],
+        )
+        .unwrap();
+        assert_eq!(change["status"], "Complete");
+        assert!(cli(&client, &["get-record-sets", "--zone", "ok."]).unwrap()[0].is_null());
+    }
+
+    #[test]
+    fn test_cli_import_zone() {
+        let (_server, client, zone) = setup();
+        let path = temp_file(
+            "import",
+            "$TTL 300\n@ NS ns1.example.com.\nwww A 10.0.0.1\n",
+        );
+        let dry_run = cli(
+            &client,
+            &[
+                "import-zone",
+                "--id",
+                &zone.id,
+                "--file",
+                &path,
+                "--dry-run",
+            ],
+        )
+        .unwrap();
+        // the apex NS is left to the server
+        assert_eq!(dry_run[0]["name"], "www");
+        assert!(dry_run[1].is_null());
+        assert!(cli(&client, &["get-record-sets", "--id", &zone.id]).unwrap()[0].is_null());
+        let changes = cli(
+            &client,
+            &["import-zone", "--id", &zone.id, "--file", &path, "--wait"],
+        )
+        .unwrap();
+        assert_eq!(changes[0]["status"], "Complete");
+
+        // importing again fails on www, after creating mail
+        std::fs::write(&path, "$TTL 300\nmail A 10.0.0.2\nwww A 10.0.0.1\n").unwrap();
+        let err = cli(&client, &["import-zone", "--id", &zone.id, "--file", &path]).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        let import = err.downcast_ref::<zone_file::ImportError>().unwrap();
+        assert_eq!(import.created[0].record_set.name, "mail");
+        assert!(err
+            .to_string()
+            .starts_with("failed to import www A after creating 1 record sets (mail A): conflict"));
+        assert_eq!(exit_code(&err), 6);
+    }
+
+    #[test]
+    fn test_cli_export_zone() {
+        let (_server, client, zone) = setup();
+        create_record_set(&client, &zone, &a("www", 300));
+
+        let matches = app().get_matches_from(vec!["vinyldns", "export-zone", "--zone", "ok."]);
+        let zone_file = run(&client, &matches).unwrap();
+        assert_eq!(
+            zone_file,
+            "$ORIGIN ok.\n$TTL 300\nwww                             IN A     10.0.0.1\n"
+        );
+    }
+
+    #[test]
+    fn test_cli_diff_zone() {
+        let (_server, client, zone) = setup();
+        create_record_set(&client, &zone, &a("www", 300));
+
+        let path = temp_file(
+            "diff",
+            "@ 300 NS ns1.example.com.\nWWW.ok. 600 A 10.0.0.1\n",
+        );
+        let diff = cli(
+            &client,
+            &["diff-zone", "--zone", "ok", "--file", &path, "--json"],
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(diff["changed"][0]["ttl"]["new"], 600);
+        assert!(diff["added"][0].is_null() && diff["removed"][0].is_null());
+    }
+
+    #[test]
+    fn test_cli_plan_apply() {
+        let (_server, client, zone) = setup();
+        let unmanaged = api_types::RecordSet::new(
+            "other",
+            300,
+            vec![api_types::RecordData::CNAME {
+                cname: "ok.".to_string(),
+            }],
+        );
+        create_record_set(&client, &zone, &unmanaged);
+
+        let path = temp_file(
+            "plan.yaml",
+            "zone: ok\nowner: me\nttl: 300\nrecordSets:\n  - name: www\n    type: A\n    records:\n      - address: 10.0.0.1\n",
+        );
+        let run_args = |args: &[&str]| {
+            let matches =
+                app().get_matches_from(std::iter::once("vinyldns").chain(args.iter().cloned()));
+            run(&client, &matches).unwrap()
+        };
+        assert_eq!(
+            run_args(&["plan", "--file", &path]),
+            "+ _owner.www TXT ttl 300\n    + \"owner=me type=A\"\n+ www A ttl 300\n    + 10.0.0.1\n2 to create, 0 to update, 0 to delete"
+        );
+        run_args(&["apply", "--file", &path, "--wait"]);
+        assert_eq!(run_args(&["plan", "--file", &path]), "no changes");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(client.record_sets(&zone.id).unwrap().len(), 3);
+
+        let path = temp_file("nope.yaml", "zone: nope\nrecordSets: []\n");
+        let err = cli(&client, &["plan", "--file", &path]).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.to_string(), "no zone with name or id nope");
+        assert_eq!(exit_code(&err), 5);
+    }
+
+    #[test]
+    fn test_cli_exit_codes() {
+        let server = MockServer::start();
+        let client = client::Client::new("testUserAccessKey", "wrong", &server.host());
+        assert_eq!(exit_code(&cli(&client, &["list-zones"]).unwrap_err()), 4);
+
+        let client = server.client();
+        let err = cli(
+            &client,
+            &[
+                "create-zone",
+                "--name",
+                "ok.",
+                "--email",
+                "test@example.com",
+                "--admin-group-id",
+                "nope",
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(exit_code(&err), 3);
+    }
+
+    #[test]
+    fn test_cli_sync_zone() {
+        let (server, client, zone) = setup();
+        let sync_zone = |args: &[&str]| {
+            let matches = app().get_matches_from(
+                ["vinyldns", "sync-zone", "--zone", "ok."]
+                    .iter()
+                    .chain(args.iter()),
+            );
+            run(&client, &matches).unwrap()
+        };
 
-    println!("{}", out);
+        let out = sync_zone(&["--wait"]);
+        assert!(out.starts_with("ok. has not been synced\nsync "));
+        assert!(out.ends_with(" is Synced"));
+
+        server.pending_requests(10);
+        let out = sync_zone(&[]);
+        assert!(out.starts_with("ok. was last synced just now\nstarted sync "));
+        let id = out.rsplit(' ').next().unwrap();
+        assert_eq!(
+            sync_zone(&[]),
+            format!(
+                "ok. was last synced just now\nsync {} is already in progress",
+                id
+            )
+        );
+        let err = client.zone_sync(&zone.id).unwrap_err();
+        assert_eq!(exit_code(&err), 6);
+    }
+
+    #[test]
+    fn test_cli_search_records() {
+        let (_server, client, zone) = setup();
+        let mut owned = a("www2", 300);
+        owned.owner_group_id = Some(zone.admin_group_id.clone());
+        for rs in &[a("www", 300), owned, a("mail", 300)] {
+            create_record_set(&client, &zone, rs);
+        }
+
+        let found = cli(
+            &client,
+            &["search-records", "--name", "www*", "--sort", "DESC"],
+        )
+        .unwrap();
+        assert_eq!(found[0]["name"], "www2");
+        assert_eq!(found[0]["zoneName"], "ok.");
+        assert_eq!(found[0]["ownerGroupName"], "ok-group");
+        assert_eq!(found[1]["name"], "www");
+        assert!(found[2].is_null());
+        let found = cli(
+            &client,
+            &[
+                "search-records",
+                "--name",
+                "*.ok",
+                "--type",
+                "A,AAAA",
+                "--owner-group",
+                "ok-group",
+            ],
+        )
+        .unwrap();
+        assert_eq!(found.as_array().unwrap().len(), 1);
+        let found = cli(
+            &client,
+            &["search-records", "--name", "mail.ok", "--type", "CNAME"],
+        )
+        .unwrap();
+        assert!(found[0].is_null());
+        let found = cli(
+            &client,
+            &[
+                "get-record-sets",
+                "--zone",
+                "ok.",
+                "--name",
+                "ww",
+                "--owner-group",
+                "ok-group",
+            ],
+        )
+        .unwrap();
+        assert_eq!(found[0]["name"], "www2");
+        assert!(found[1].is_null());
+        let found = cli(
+            &client,
+            &["get-record-sets", "--zone", "ok.", "--type", "A"],
+        )
+        .unwrap();
+        assert_eq!(found.as_array().unwrap().len(), 3);
+        let err = cli(&client, &["search-records", "--name", "*"]).unwrap_err();
+        assert_eq!(exit_code(&err), 3);
+    }
+
+    #[test]
+    fn test_cli_acl() {
+        let (_server, client, zone) = setup();
+
+        let rules = cli(
+            &client,
+            &[
+                "acl",
+                "add",
+                "--zone",
+                "ok.",
+                "--access",
+                "Write",
+                "--group",
+                "ok-group",
+                "--record-types",
+                "A,AAAA",
+                "--wait",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            serde_json::json!([{
+                "accessLevel": "Write",
+                "groupId": zone.admin_group_id,
+                "recordTypes": ["A", "AAAA"],
+            }])
+        );
+        let rules = cli(
+            &client,
+            &[
+                "acl", "add", "--id", &zone.id, "--access", "Read", "--user", "testuser", "--wait",
+            ],
+        )
+        .unwrap();
+        assert_eq!(rules[1]["userId"], "testuser");
+
+        let rules = cli(
+            &client,
+            &[
+                "acl",
+                "remove",
+                "--zone",
+                "ok.",
+                "--access",
+                "Write",
+                "--group",
+                "ok-group",
+                "--record-types",
+                "A,AAAA",
+                "--wait",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            cli(&client, &["acl", "list", "--zone", "ok."]).unwrap()
+        );
+        assert_eq!(rules[0]["accessLevel"], "Read");
+        assert!(rules[1].is_null());
+
+        let err = cli(
+            &client,
+            &[
+                "acl", "add", "--zone", "ok.", "--access", "Read", "--group", "nope",
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "no group with name or id nope");
+        assert_eq!(exit_code(&err), 5);
+    }
 }
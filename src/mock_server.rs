@@ -0,0 +1,1539 @@
+//! A fake VinylDNS server for tests, speaking just enough HTTP/1.1 for `Client` to talk to it.
+//!
+//! It keeps zones, record sets, groups and their change histories in memory, rejects requests
+//! whose SigV4 `Authorization` header doesn't verify, and leaves changes Pending for a number
+//! of requests before completing them.
+
+use crate::api_types::*;
+use crate::aws_sign;
+use crate::client::{Client, RetryPolicy};
+use crate::names;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub const ACCESS_KEY: &str = "testUserAccessKey";
+pub const SECRET_KEY: &str = "testUserSecretKey";
+
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Listen on a free local port until dropped
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        // a broken connection only fails the request it was carrying
+                        let _ = serve(stream, &state);
+                    }
+                }
+            })
+        };
+
+        MockServer {
+            addr,
+            state,
+            shutdown,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn host(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A client signing requests as the test user, which doesn't retry so that every request
+    /// a test makes counts towards completing pending changes exactly once
+    pub fn client(&self) -> Client {
+        Client::new(ACCESS_KEY, SECRET_KEY, &self.host()).with_retry_policy(RetryPolicy::none())
+    }
+
+    /// Number of further requests a change stays Pending for, defaults to 1
+    pub fn pending_requests(&self, pending_requests: u32) {
+        self.state.lock().unwrap().pending_requests = pending_requests;
+    }
+
+    /// Make the next change fail with `message` once it is processed
+    pub fn fail_next_change(&self, message: &str) {
+        self.state.lock().unwrap().fail_next = Some(message.to_string());
+    }
+
+    /// Answer the next `count` requests with `status` instead of handling them
+    pub fn fail_requests(&self, status: u16, count: u32) {
+        self.state.lock().unwrap().failed_requests = Some((status, count)).filter(|_| count > 0);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake up the listener so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Request {
+    method: reqwest::Method,
+    /// the path and query exactly as they were sent
+    target: String,
+    url: reqwest::Url,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn param(&self, key: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Ok(()),
+    };
+
+    let mut headers = HeaderMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.trim().as_bytes()),
+                HeaderValue::from_str(value.trim()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+    }
+
+    let length = headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let host = headers
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost")
+        .to_string();
+    let (status, body) = match (method.parse(), format!("http://{}{}", host, target).parse()) {
+        (Ok(method), Ok(url)) => {
+            let request = Request {
+                method,
+                target,
+                url,
+                headers,
+                body,
+            };
+            state.lock().unwrap().handle(&request)
+        }
+        _ => (400, "Malformed request".to_string()),
+    };
+
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("");
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Check the request was signed with a known access key's secret.
+///
+/// The signature is recomputed here from the request as it arrived, independently of
+/// `aws_sign`, so a signing bug in the client shows up as a rejected request.
+fn verify(request: &Request) -> Result<(), String> {
+    let auth = request
+        .headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .ok_or("Authorization header not found")?;
+    let fields = auth
+        .strip_prefix("AWS4-HMAC-SHA256 ")
+        .ok_or("Authorization header is not AWS4-HMAC-SHA256")?;
+    let field = |name: &str| {
+        fields
+            .split(", ")
+            .find_map(|f| f.strip_prefix(name)?.strip_prefix('='))
+            .ok_or(format!("Authorization header is missing {}", name))
+    };
+    let credential: Vec<_> = field("Credential")?.split('/').collect();
+    let signed_headers = field("SignedHeaders")?;
+    let (access_key, date, region, service) = match credential.as_slice() {
+        [access_key, date, region, service, "aws4_request"] => {
+            (*access_key, *date, *region, *service)
+        }
+        _ => return Err("Authorization header has a malformed Credential".to_string()),
+    };
+    if access_key != ACCESS_KEY {
+        return Err(format!("Account with accessKey {} not found", access_key));
+    }
+
+    let amz_date = request
+        .headers
+        .get("x-amz-date")
+        .and_then(|v| v.to_str().ok())
+        .ok_or("X-Amz-Date header not found")?;
+    if !amz_date.starts_with(date) || !signed_headers.split(';').any(|h| h == "host") {
+        return Err("Request signature could not be validated".to_string());
+    }
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    if signature(request, signed_headers, amz_date, &scope, SECRET_KEY) != field("Signature")? {
+        return Err("Request signature could not be validated".to_string());
+    }
+    Ok(())
+}
+
+/// The SigV4 signature of `request`, following
+/// https://docs.aws.amazon.com/general/latest/gr/sigv4_signing.html step by step
+fn signature(
+    request: &Request,
+    signed_headers: &str,
+    amz_date: &str,
+    scope: &str,
+    secret: &str,
+) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_varkey(key).unwrap();
+        mac.input(data.as_bytes());
+        mac.result().code().to_vec()
+    }
+    // URI-encode everything but the unreserved characters
+    fn encode(s: &str) -> String {
+        s.bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    let (path, _) = request
+        .target
+        .split_once('?')
+        .unwrap_or((&request.target, ""));
+    let mut query: Vec<_> = request
+        .url
+        .query_pairs()
+        .map(|(k, v)| format!("{}={}", encode(&k), encode(&v)))
+        .collect();
+    query.sort();
+    let headers: String = signed_headers
+        .split(';')
+        .map(|name| {
+            let values: Vec<_> = request
+                .headers
+                .get_all(name)
+                .iter()
+                .map(|v| {
+                    let v = v.to_str().unwrap_or_default();
+                    v.split_whitespace().collect::<Vec<_>>().join(" ")
+                })
+                .collect();
+            format!("{}:{}\n", name, values.join(","))
+        })
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{:x}",
+        request.method,
+        path,
+        query.join("&"),
+        headers,
+        signed_headers,
+        Sha256::digest(&request.body)
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        amz_date,
+        scope,
+        Sha256::digest(canonical_request.as_bytes())
+    );
+
+    let mut key = format!("AWS4{}", secret).into_bytes();
+    for part in scope.split('/') {
+        key = hmac(&key, part);
+    }
+    hmac(&key, &string_to_sign)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn json<T: Serialize>(status: u16, body: &T) -> (u16, String) {
+    (status, serde_json::to_string(body).unwrap())
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    (status, message.to_string())
+}
+
+/// Whether `name` matches `pattern`, where `*` matches anything, ignoring case
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.to_lowercase(), name.to_lowercase());
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if parts.len() == 1 {
+        return name == first;
+    }
+    if !name.starts_with(first) || name.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    name.ends_with(last)
+}
+
+/// Whether a record set is of a type in `recordTypeFilter` and owned by `recordOwnerGroupFilter`
+fn record_set_filtered(request: &Request, rs: &RecordSet) -> bool {
+    let types = request.param("recordTypeFilter").unwrap_or_default();
+    let types: Vec<RecordType> = types
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.parse().unwrap())
+        .collect();
+    let owner_group = request.param("recordOwnerGroupFilter");
+    (types.is_empty() || types.contains(&rs.record_set_type))
+        && (owner_group.is_none() || rs.owner_group_id == owner_group)
+}
+
+/// Sort items by name as `nameSort` says, ascending by default
+fn sort_by_name<T>(
+    request: &Request,
+    items: &mut [T],
+    name: impl Fn(&T) -> &str,
+) -> Option<NameSort> {
+    items.sort_by(|a, b| name(a).cmp(name(b)));
+    let name_sort = request.param("nameSort").map(|s| s.parse().unwrap());
+    if name_sort == Some(NameSort::Desc) {
+        items.reverse();
+    }
+    name_sort
+}
+
+fn now() -> String {
+    aws_sign::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+/// Items at `startFrom` (an offset) onwards, and the `nextId` of the following page
+fn page<T: Clone>(request: &Request, items: &[T]) -> (Vec<T>, Option<String>, Option<u32>) {
+    let start: usize = request
+        .param("startFrom")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    let max_items: Option<u32> = request.param("maxItems").and_then(|s| s.parse().ok());
+    let end = std::cmp::min(items.len(), start + max_items.unwrap_or(100) as usize);
+    let page = items.get(start..end).unwrap_or_default().to_vec();
+    let next_id = if end < items.len() {
+        Some(end.to_string())
+    } else {
+        None
+    };
+    (page, next_id, max_items)
+}
+
+struct Pending<T> {
+    change: T,
+    requests_left: u32,
+    fail: Option<String>,
+}
+
+struct State {
+    user: User,
+    zones: Vec<Zone>,
+    zone_changes: Vec<Pending<ZoneChange>>,
+    record_sets: Vec<RecordSet>,
+    record_set_changes: Vec<Pending<RecordSetChange>>,
+    groups: Vec<Group>,
+    group_changes: Vec<(String, GroupChange)>,
+    pending_requests: u32,
+    fail_next: Option<String>,
+    failed_requests: Option<(u16, u32)>,
+    ids: u64,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            user: User {
+                id: "testuser".to_string(),
+                user_name: "testuser".to_string(),
+                first_name: "Test".to_string(),
+                last_name: "User".to_string(),
+                email: "test@example.com".to_string(),
+                created: now(),
+            },
+            zones: vec![],
+            zone_changes: vec![],
+            record_sets: vec![],
+            record_set_changes: vec![],
+            groups: vec![],
+            group_changes: vec![],
+            pending_requests: 1,
+            fail_next: None,
+            failed_requests: None,
+            ids: 0,
+        }
+    }
+
+    fn id(&mut self) -> String {
+        self.ids += 1;
+        format!("00000000-0000-0000-0000-{:012}", self.ids)
+    }
+
+    fn handle(&mut self, request: &Request) -> (u16, String) {
+        if let Err(message) = verify(request) {
+            return error(401, &message);
+        }
+        if let Some((status, count)) = self.failed_requests.take() {
+            if count > 1 {
+                self.failed_requests = Some((status, count - 1));
+            }
+            return error(status, "injected failure");
+        }
+        self.advance();
+
+        let path: Vec<&str> = request
+            .url
+            .path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        match (request.method.as_str(), path.as_slice()) {
+            ("GET", ["zones"]) => self.zones(request),
+            ("POST", ["zones"]) => self.zone_create(request),
+            ("GET", ["zones", "name", name]) => {
+                let name = names::absolute(name);
+                match self
+                    .zones
+                    .iter()
+                    .find(|z| names::absolute(&z.name).eq_ignore_ascii_case(&name))
+                {
+                    Some(zone) => json(200, &ZoneResponse { zone: zone.clone() }),
+                    None => error(404, &format!("Zone with name {} does not exist", name)),
+                }
+            }
+            ("GET", ["zones", id]) => match self.zone(id) {
+                Some(zone) => json(200, &ZoneResponse { zone: zone.clone() }),
+                None => self.zone_not_found(id),
+            },
+            ("PUT", ["zones", id]) => self.zone_update(id, request),
+            ("DELETE", ["zones", id]) => self.zone_delete(id),
+            ("GET", ["zones", id, "changes"]) => self.zone_changes(id, request),
+            ("POST", ["zones", id, "sync"]) => self.zone_sync(id),
+            ("PUT", ["zones", id, "acl", "rules"]) => self.acl_rule_change(id, request, true),
+            ("DELETE", ["zones", id, "acl", "rules"]) => self.acl_rule_change(id, request, false),
+            ("GET", ["recordsets"]) => self.record_sets_search(request),
+            ("GET", ["users", user]) => {
+                if self.user.id == *user || self.user.user_name == *user {
+                    json(200, &self.user)
+                } else {
+                    error(404, &format!("User {} was not found", user))
+                }
+            }
+            ("GET", ["zones", id, "recordsets"]) => self.record_sets(id, request),
+            ("POST", ["zones", id, "recordsets"]) => self.record_set_create(id, request),
+            ("GET", ["zones", zone_id, "recordsets", id]) => match self.record_set(zone_id, id) {
+                Some(rs) => json(200, rs),
+                None => self.record_set_not_found(id),
+            },
+            ("PUT", ["zones", zone_id, "recordsets", id]) => {
+                self.record_set_update(zone_id, id, request)
+            }
+            ("DELETE", ["zones", zone_id, "recordsets", id]) => self.record_set_delete(zone_id, id),
+            ("GET", ["zones", _, "recordsets", _, "changes", id]) => {
+                match self.record_set_changes.iter().find(|c| c.change.id == *id) {
+                    Some(change) => json(200, &change.change),
+                    None => error(404, &format!("RecordSetChange with id {} not found", id)),
+                }
+            }
+            ("GET", ["zones", id, "recordsetchanges"]) => self.record_set_changes(id, request),
+            ("GET", ["groups"]) => self.groups(request),
+            ("POST", ["groups"]) => self.group_create(request),
+            ("GET", ["groups", id]) => match self.group(id) {
+                Some(group) => json(200, group),
+                None => self.group_not_found(id),
+            },
+            ("PUT", ["groups", id]) => self.group_update(id, request),
+            ("DELETE", ["groups", id]) => self.group_delete(id),
+            ("GET", ["groups", id, "admins"]) => match self.group(id) {
+                Some(group) => json(
+                    200,
+                    &GroupAdmins {
+                        admins: group.admins.clone(),
+                    },
+                ),
+                None => self.group_not_found(id),
+            },
+            ("GET", ["groups", id, "members"]) => match self.group(id) {
+                Some(group) => json(
+                    200,
+                    &GroupMembers {
+                        members: group.members.clone(),
+                    },
+                ),
+                None => self.group_not_found(id),
+            },
+            ("GET", ["groups", id, "activity"]) => match self.group(id) {
+                Some(_) => json(
+                    200,
+                    &GroupChanges {
+                        changes: self
+                            .group_changes
+                            .iter()
+                            .filter(|(group_id, _)| group_id == id)
+                            .map(|(_, change)| change.clone())
+                            .collect(),
+                    },
+                ),
+                None => self.group_not_found(id),
+            },
+            _ => error(404, "The requested path was not found"),
+        }
+    }
+
+    /// Count down pending changes, completing those which have waited long enough
+    fn advance(&mut self) {
+        let mut done = vec![];
+        for pending in self.zone_changes.iter_mut() {
+            if pending.change.status == ZoneChangeStatus::Pending {
+                pending.requests_left = pending.requests_left.saturating_sub(1);
+                if pending.requests_left == 0 {
+                    done.push(pending.change.id.clone());
+                }
+            }
+        }
+        for id in done {
+            self.complete_zone_change(&id);
+        }
+
+        let mut done = vec![];
+        for pending in self.record_set_changes.iter_mut() {
+            if pending.change.status == RecordSetChangeStatus::Pending {
+                pending.requests_left = pending.requests_left.saturating_sub(1);
+                if pending.requests_left == 0 {
+                    done.push(pending.change.id.clone());
+                }
+            }
+        }
+        for id in done {
+            self.complete_record_set_change(&id);
+        }
+    }
+
+    fn complete_zone_change(&mut self, id: &str) {
+        let pending = self
+            .zone_changes
+            .iter_mut()
+            .find(|p| p.change.id == id)
+            .unwrap();
+        let change = &mut pending.change;
+        let zone_id = change.zone.id.clone();
+        let status = match (&pending.fail, &change.change_type) {
+            (Some(_), ZoneChangeType::Create) => Some(ZoneStatus::Deleted),
+            (Some(_), _) => Some(ZoneStatus::Active),
+            (None, ZoneChangeType::Delete) => Some(ZoneStatus::Deleted),
+            (None, _) => Some(ZoneStatus::Active),
+        };
+        change.status = match &pending.fail {
+            Some(_) => ZoneChangeStatus::Failed,
+            None => ZoneChangeStatus::Synced,
+        };
+        change.system_message = pending.fail.clone();
+        change.zone.status = status.clone();
+        let synced = pending.fail.is_none()
+            && matches!(
+                change.change_type,
+                ZoneChangeType::Sync | ZoneChangeType::AutomatedSync
+            );
+        if synced {
+            change.zone.latest_sync = now();
+        }
+
+        if status == Some(ZoneStatus::Deleted) {
+            self.zones.retain(|z| z.id != zone_id);
+            self.record_sets.retain(|rs| rs.zone_id != zone_id);
+        } else if let Some(zone) = self.zones.iter_mut().find(|z| z.id == zone_id) {
+            zone.status = status;
+            if synced {
+                zone.latest_sync = now();
+            }
+        }
+    }
+
+    fn complete_record_set_change(&mut self, id: &str) {
+        let pending = self
+            .record_set_changes
+            .iter_mut()
+            .find(|p| p.change.id == id)
+            .unwrap();
+        let change = &mut pending.change;
+        let rs_id = change.record_set.id.clone();
+        let remove = match (&pending.fail, &change.change_type) {
+            (Some(_), RecordSetChangeType::Create) => true,
+            (Some(_), _) => false,
+            (None, RecordSetChangeType::Delete) => true,
+            (None, _) => false,
+        };
+        change.status = match &pending.fail {
+            Some(_) => RecordSetChangeStatus::Failed,
+            None => RecordSetChangeStatus::Complete,
+        };
+        change.system_message = pending.fail.clone();
+        change.record_set.status = Some(if remove {
+            RecordSetStatus::Inactive
+        } else {
+            RecordSetStatus::Active
+        });
+
+        if remove {
+            self.record_sets.retain(|rs| rs.id != rs_id);
+        } else if let Some(rs) = self.record_sets.iter_mut().find(|rs| rs.id == rs_id) {
+            rs.status = Some(RecordSetStatus::Active);
+        }
+    }
+
+    fn zone(&self, id: &str) -> Option<&Zone> {
+        self.zones.iter().find(|z| z.id == id)
+    }
+
+    fn zone_not_found(&self, id: &str) -> (u16, String) {
+        error(404, &format!("Zone with id {} does not exist", id))
+    }
+
+    fn zone_pending(&self, id: &str) -> bool {
+        self.zone_changes
+            .iter()
+            .any(|p| p.change.zone.id == id && p.change.status == ZoneChangeStatus::Pending)
+    }
+
+    fn zones(&self, request: &Request) -> (u16, String) {
+        let name_filter = request.param("nameFilter");
+        let zones: Vec<_> = self
+            .zones
+            .iter()
+            .filter(|z| name_filter.as_ref().is_none_or(|f| z.name.contains(f)))
+            .cloned()
+            .collect();
+        let (zones, next_id, max_items) = page(request, &zones);
+        json(
+            200,
+            &Zones {
+                zones,
+                name_filter,
+                start_from: request.param("startFrom"),
+                next_id,
+                max_items,
+                ignore_access: request.param("ignoreAccess").as_deref() == Some("true"),
+            },
+        )
+    }
+
+    fn zone_change(&mut self, zone: Zone, change_type: ZoneChangeType) -> ZoneChange {
+        let change = ZoneChange {
+            zone,
+            user_id: self.user.id.clone(),
+            change_type,
+            status: ZoneChangeStatus::Pending,
+            created: now(),
+            id: self.id(),
+            system_message: None,
+        };
+        self.zone_changes.insert(
+            0,
+            Pending {
+                change: change.clone(),
+                requests_left: self.pending_requests,
+                fail: self.fail_next.take(),
+            },
+        );
+        change
+    }
+
+    fn zone_create(&mut self, request: &Request) -> (u16, String) {
+        let mut zone: Zone = match serde_json::from_slice(&request.body) {
+            Ok(zone) => zone,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        let mut errors = vec![];
+        if zone.name.is_empty() {
+            errors.push("Missing Zone.name");
+        }
+        if zone.email.is_empty() {
+            errors.push("Missing Zone.email");
+        }
+        if zone.admin_group_id.is_empty() {
+            errors.push("Missing Zone.adminGroupId");
+        }
+        if !errors.is_empty() {
+            return json(400, &serde_json::json!({ "errors": errors }));
+        }
+        if self.group(&zone.admin_group_id).is_none() {
+            return error(
+                400,
+                &format!("Admin group with ID {} does not exist", zone.admin_group_id),
+            );
+        }
+        if self.zones.iter().any(|z| z.name == zone.name) {
+            return error(409, &format!("Zone with name {} already exists", zone.name));
+        }
+
+        zone.id = self.id();
+        zone.created = now();
+        zone.status = Some(ZoneStatus::PendingUpdate);
+        self.zones.push(zone.clone());
+        let change = self.zone_change(zone, ZoneChangeType::Create);
+        json(202, &change)
+    }
+
+    fn zone_update(&mut self, id: &str, request: &Request) -> (u16, String) {
+        let mut zone: Zone = match serde_json::from_slice(&request.body) {
+            Ok(zone) => zone,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        let existing = match self.zone(id) {
+            Some(existing) => existing.clone(),
+            None => return self.zone_not_found(id),
+        };
+        if self.zone_pending(id) {
+            return error(409, &format!("Zone {} has a pending change", id));
+        }
+
+        zone.id = existing.id;
+        zone.created = existing.created;
+        zone.updated = now();
+        zone.status = Some(ZoneStatus::PendingUpdate);
+        if let Some(z) = self.zones.iter_mut().find(|z| z.id == id) {
+            *z = zone.clone();
+        }
+        let change = self.zone_change(zone, ZoneChangeType::Update);
+        json(202, &change)
+    }
+
+    fn zone_delete(&mut self, id: &str) -> (u16, String) {
+        if self.zone(id).is_none() {
+            return self.zone_not_found(id);
+        }
+        if self.zone_pending(id) {
+            return error(409, &format!("Zone {} has a pending change", id));
+        }
+        let zone = {
+            let zone = self.zones.iter_mut().find(|z| z.id == id).unwrap();
+            zone.status = Some(ZoneStatus::PendingDelete);
+            zone.clone()
+        };
+        let change = self.zone_change(zone, ZoneChangeType::Delete);
+        json(202, &change)
+    }
+
+    /// Add the rule in the request to the zone's ACL, or remove it
+    fn acl_rule_change(&mut self, id: &str, request: &Request, add: bool) -> (u16, String) {
+        let rule: ACLRule = match serde_json::from_slice(&request.body) {
+            Ok(rule) => rule,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        if rule.user_id.is_some() && rule.group_id.is_some() {
+            return error(400, "Cannot specify both a userId and a groupId");
+        }
+        if self.zone(id).is_none() {
+            return self.zone_not_found(id);
+        }
+        if self.zone_pending(id) {
+            return error(409, &format!("Zone {} has a pending change", id));
+        }
+        let zone = {
+            let zone = self.zones.iter_mut().find(|z| z.id == id).unwrap();
+            zone.acl.rules.retain(|r| *r != rule);
+            if add {
+                zone.acl.rules.push(rule);
+            }
+            zone.updated = now();
+            zone.status = Some(ZoneStatus::PendingUpdate);
+            zone.clone()
+        };
+        let change = self.zone_change(zone, ZoneChangeType::Update);
+        json(202, &change)
+    }
+
+    fn zone_sync(&mut self, id: &str) -> (u16, String) {
+        let pending = self.zone_pending(id);
+        let zone = match self.zones.iter_mut().find(|z| z.id == id) {
+            Some(zone) => zone,
+            None => return self.zone_not_found(id),
+        };
+        if zone.status == Some(ZoneStatus::Syncing) {
+            return error(409, &format!("Zone {} is currently syncing", zone.name));
+        }
+        if pending {
+            return error(409, &format!("Zone {} is currently unavailable", zone.name));
+        }
+        zone.status = Some(ZoneStatus::Syncing);
+        let zone = zone.clone();
+        let change = self.zone_change(zone, ZoneChangeType::Sync);
+        json(202, &change)
+    }
+
+    fn zone_changes(&self, id: &str, request: &Request) -> (u16, String) {
+        // like VinylDNS, a deleted zone's history goes with it
+        if self.zone(id).is_none() {
+            return self.zone_not_found(id);
+        }
+        let changes: Vec<_> = self
+            .zone_changes
+            .iter()
+            .filter(|p| p.change.zone.id == id)
+            .map(|p| p.change.clone())
+            .collect();
+        let (zone_changes, next_id, max_items) = page(request, &changes);
+        json(
+            200,
+            &ZoneChanges {
+                zone_id: id.to_string(),
+                zone_changes,
+                start_from: request.param("startFrom"),
+                next_id,
+                max_items,
+            },
+        )
+    }
+
+    fn record_set(&self, zone_id: &str, id: &str) -> Option<&RecordSet> {
+        self.record_sets
+            .iter()
+            .find(|rs| rs.zone_id == zone_id && rs.id == id)
+    }
+
+    fn record_set_not_found(&self, id: &str) -> (u16, String) {
+        error(404, &format!("RecordSet with id {} does not exist", id))
+    }
+
+    fn record_set_pending(&self, id: &str) -> bool {
+        self.record_set_changes.iter().any(|p| {
+            p.change.record_set.id == id && p.change.status == RecordSetChangeStatus::Pending
+        })
+    }
+
+    fn record_sets(&self, zone_id: &str, request: &Request) -> (u16, String) {
+        if self.zone(zone_id).is_none() {
+            return self.zone_not_found(zone_id);
+        }
+        // without a wildcard the name filter matches any name containing it
+        let name_filter = request
+            .param("recordNameFilter")
+            .map(|f| match f.contains('*') {
+                true => f,
+                false => format!("*{}*", f),
+            });
+        let mut record_sets: Vec<_> = self
+            .record_sets
+            .iter()
+            .filter(|rs| rs.zone_id == zone_id)
+            .filter(|rs| name_filter.as_ref().is_none_or(|f| glob_match(f, &rs.name)))
+            .filter(|rs| record_set_filtered(request, rs))
+            .cloned()
+            .collect();
+        sort_by_name(request, &mut record_sets, |rs| &rs.name);
+        let (record_sets, next_id, max_items) = page(request, &record_sets);
+        json(
+            200,
+            &RecordSetsResponse {
+                record_sets,
+                start_from: request.param("startFrom"),
+                next_id,
+                max_items,
+            },
+        )
+    }
+
+    /// Record sets of every zone whose name, or fully qualified name, matches `recordNameFilter`
+    fn record_sets_search(&self, request: &Request) -> (u16, String) {
+        let name_filter = request.param("recordNameFilter").unwrap_or_default();
+        if name_filter.chars().filter(|c| c.is_alphanumeric()).count() < 2 {
+            return error(
+                400,
+                "recordNameFilter must contain at least two letters or digits",
+            );
+        }
+        let mut record_sets: Vec<_> = self
+            .record_sets
+            .iter()
+            .filter_map(|rs| {
+                let zone = self.zone(&rs.zone_id)?;
+                let fqdn = match rs.name.as_str() {
+                    "@" => zone.name.clone(),
+                    name => format!("{}.{}", name, zone.name),
+                };
+                let matches = glob_match(&name_filter, &rs.name)
+                    || glob_match(&name_filter, fqdn.trim_end_matches('.'));
+                Some(rs).filter(|_| matches).map(|rs| (rs, zone))
+            })
+            .filter(|(rs, _)| record_set_filtered(request, rs))
+            .map(|(rs, zone)| RecordSetInfo {
+                record_set: rs.clone(),
+                zone_name: zone.name.clone(),
+                zone_shared: false,
+                zone_admin_group_id: zone.admin_group_id.clone(),
+                owner_group_name: rs
+                    .owner_group_id
+                    .as_ref()
+                    .and_then(|id| self.group(id))
+                    .map(|g| g.name.clone()),
+            })
+            .collect();
+        let name_sort = sort_by_name(request, &mut record_sets, |rs| &rs.record_set.name);
+        let (record_sets, next_id, max_items) = page(request, &record_sets);
+        json(
+            200,
+            &RecordSetSearchResponse {
+                record_sets,
+                start_from: request.param("startFrom"),
+                next_id,
+                max_items,
+                record_name_filter: Some(name_filter),
+                name_sort,
+            },
+        )
+    }
+
+    fn record_set_change(
+        &mut self,
+        zone: Zone,
+        record_set: RecordSet,
+        change_type: RecordSetChangeType,
+    ) -> RecordSetChange {
+        let change = RecordSetChange {
+            zone,
+            record_set,
+            user_id: self.user.id.clone(),
+            change_type,
+            status: RecordSetChangeStatus::Pending,
+            created: now(),
+            id: self.id(),
+            system_message: None,
+        };
+        self.record_set_changes.insert(
+            0,
+            Pending {
+                change: change.clone(),
+                requests_left: self.pending_requests,
+                fail: self.fail_next.take(),
+            },
+        );
+        change
+    }
+
+    fn record_set_create(&mut self, zone_id: &str, request: &Request) -> (u16, String) {
+        let mut rs: RecordSet = match serde_json::from_slice(&request.body) {
+            Ok(rs) => rs,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        let zone = match self.zone(zone_id) {
+            Some(zone) => zone.clone(),
+            None => return self.zone_not_found(zone_id),
+        };
+        if rs.record_set_type == RecordType::NS
+            && (rs.name == "@"
+                || names::absolute(&rs.name).eq_ignore_ascii_case(&names::absolute(&zone.name)))
+        {
+            return error(
+                422,
+                &format!(
+                    "Record with name [{}] is an NS record at apex and cannot be added",
+                    rs.name
+                ),
+            );
+        }
+        if self.record_sets.iter().any(|existing| {
+            existing.zone_id == zone_id
+                && existing.name == rs.name
+                && existing.record_set_type == rs.record_set_type
+        }) {
+            return error(
+                409,
+                &format!(
+                    "RecordSet with name {} and type {} already exists in zone {}",
+                    rs.name, rs.record_set_type, zone.name
+                ),
+            );
+        }
+
+        rs.id = self.id();
+        rs.zone_id = zone_id.to_string();
+        rs.created = now();
+        rs.account = self.user.id.clone();
+        rs.status = Some(RecordSetStatus::Pending);
+        self.record_sets.push(rs.clone());
+        let change = self.record_set_change(zone, rs, RecordSetChangeType::Create);
+        json(202, &change)
+    }
+
+    fn record_set_update(&mut self, zone_id: &str, id: &str, request: &Request) -> (u16, String) {
+        let mut rs: RecordSet = match serde_json::from_slice(&request.body) {
+            Ok(rs) => rs,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        let zone = match self.zone(zone_id) {
+            Some(zone) => zone.clone(),
+            None => return self.zone_not_found(zone_id),
+        };
+        let existing = match self.record_set(zone_id, id) {
+            Some(existing) => existing.clone(),
+            None => return self.record_set_not_found(id),
+        };
+        if self.record_set_pending(id) {
+            return error(409, &format!("RecordSet {} has a pending change", id));
+        }
+
+        rs.id = existing.id;
+        rs.zone_id = existing.zone_id;
+        rs.created = existing.created;
+        rs.account = existing.account;
+        rs.updated = Some(now());
+        rs.status = Some(RecordSetStatus::PendingUpdate);
+        if let Some(existing) = self.record_sets.iter_mut().find(|e| e.id == id) {
+            *existing = rs.clone();
+        }
+        let change = self.record_set_change(zone, rs, RecordSetChangeType::Update);
+        json(202, &change)
+    }
+
+    fn record_set_delete(&mut self, zone_id: &str, id: &str) -> (u16, String) {
+        let zone = match self.zone(zone_id) {
+            Some(zone) => zone.clone(),
+            None => return self.zone_not_found(zone_id),
+        };
+        if self.record_set(zone_id, id).is_none() {
+            return self.record_set_not_found(id);
+        }
+        if self.record_set_pending(id) {
+            return error(409, &format!("RecordSet {} has a pending change", id));
+        }
+        let rs = {
+            let rs = self.record_sets.iter_mut().find(|rs| rs.id == id).unwrap();
+            rs.status = Some(RecordSetStatus::PendingDelete);
+            rs.clone()
+        };
+        let change = self.record_set_change(zone, rs, RecordSetChangeType::Delete);
+        json(202, &change)
+    }
+
+    fn record_set_changes(&self, zone_id: &str, request: &Request) -> (u16, String) {
+        if self.zone(zone_id).is_none() {
+            return self.zone_not_found(zone_id);
+        }
+        let changes: Vec<_> = self
+            .record_set_changes
+            .iter()
+            .filter(|p| p.change.zone.id == zone_id)
+            .map(|p| p.change.clone())
+            .collect();
+        let (record_set_changes, next_id, max_items) = page(request, &changes);
+        json(
+            200,
+            &RecordSetChanges {
+                zone_id: zone_id.to_string(),
+                record_set_changes,
+                start_from: request.param("startFrom"),
+                next_id,
+                max_items,
+            },
+        )
+    }
+
+    fn group(&self, id: &str) -> Option<&Group> {
+        self.groups.iter().find(|g| g.id == id)
+    }
+
+    fn group_not_found(&self, id: &str) -> (u16, String) {
+        error(404, &format!("Group with ID {} was not found", id))
+    }
+
+    fn group_change(&mut self, change_type: GroupChangeType, new_group: Group, old_group: Group) {
+        self.group_changes.insert(
+            0,
+            (
+                new_group.id.clone(),
+                GroupChange {
+                    user_id: self.user.id.clone(),
+                    created: now(),
+                    change_type,
+                    new_group,
+                    old_group,
+                },
+            ),
+        );
+    }
+
+    fn groups(&self, request: &Request) -> (u16, String) {
+        let name_filter = request.param("groupNameFilter");
+        let groups: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|g| name_filter.as_ref().is_none_or(|f| g.name.contains(f)))
+            .cloned()
+            .collect();
+        let (groups, next_id, max_items) = page(request, &groups);
+        json(
+            200,
+            &Groups {
+                groups,
+                group_name_filter: name_filter,
+                start_from: request.param("startFrom"),
+                next_id,
+                max_items,
+                ignore_access: request.param("ignoreAccess").as_deref() == Some("true"),
+            },
+        )
+    }
+
+    fn group_create(&mut self, request: &Request) -> (u16, String) {
+        let mut group: Group = match serde_json::from_slice(&request.body) {
+            Ok(group) => group,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        let mut errors = vec![];
+        if group.name.is_empty() {
+            errors.push("Missing Group.name");
+        }
+        if group.email.is_empty() {
+            errors.push("Missing Group.email");
+        }
+        if !errors.is_empty() {
+            return json(400, &serde_json::json!({ "errors": errors }));
+        }
+        if self.groups.iter().any(|g| g.name == group.name) {
+            return error(
+                409,
+                &format!("Group with name {} already exists", group.name),
+            );
+        }
+
+        group.id = self.id();
+        group.created = now();
+        group.status = Some(GroupStatus::Active);
+        // the creator always administers the group
+        if !group.admins.iter().any(|u| u.id == self.user.id) {
+            group.admins.push(self.user.clone());
+        }
+        if !group.members.iter().any(|u| u.id == self.user.id) {
+            group.members.push(self.user.clone());
+        }
+        self.groups.push(group.clone());
+        self.group_change(GroupChangeType::Create, group.clone(), Group::default());
+        json(200, &group)
+    }
+
+    fn group_update(&mut self, id: &str, request: &Request) -> (u16, String) {
+        let mut group: Group = match serde_json::from_slice(&request.body) {
+            Ok(group) => group,
+            Err(e) => return error(400, &e.to_string()),
+        };
+        let old = match self.group(id) {
+            Some(old) => old.clone(),
+            None => return self.group_not_found(id),
+        };
+        group.id = old.id.clone();
+        group.created = old.created.clone();
+        group.status = old.status.clone();
+        if let Some(g) = self.groups.iter_mut().find(|g| g.id == id) {
+            *g = group.clone();
+        }
+        self.group_change(GroupChangeType::Update, group.clone(), old);
+        json(200, &group)
+    }
+
+    fn group_delete(&mut self, id: &str) -> (u16, String) {
+        let mut group = match self.group(id) {
+            Some(group) => group.clone(),
+            None => return self.group_not_found(id),
+        };
+        if let Some(zone) = self.zones.iter().find(|z| z.admin_group_id == id) {
+            return error(
+                400,
+                &format!(
+                    "{} is the admin of a zone. Cannot delete. Please transfer the ownership to another group before deleting.",
+                    zone.name
+                ),
+            );
+        }
+        self.groups.retain(|g| g.id != id);
+        group.status = Some(GroupStatus::Deleted);
+        self.group_change(GroupChangeType::Delete, group.clone(), group.clone());
+        json(200, &group)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::{ClientError, ListOptions, VinylDns, WaitOptions};
+
+    fn group(client: &Client, name: &str) -> Group {
+        client
+            .group_create(&Group {
+                name: name.to_string(),
+                email: "test@example.com".to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rejects_bad_signature() {
+        let server = MockServer::start();
+        let client = Client::new(ACCESS_KEY, "notTheSecret", &server.host());
+        match client.zones().unwrap_err().downcast::<ClientError>() {
+            Ok(ClientError::Unauthorized(r)) => {
+                assert_eq!(r.message, "Request signature could not be validated")
+            }
+            other => panic!("expected unauthorized, got {:?}", other),
+        }
+        assert!(server.client().zones().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_signature_matches_aws_test_suite() {
+        // vectors from the AWS SigV4 test suite, which all sign with this key and date
+        let cases = vec![
+            (
+                "GET",
+                "/",
+                vec![],
+                "",
+                "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+            ),
+            (
+                "GET",
+                "/?Param2=value2&Param1=value1",
+                vec![],
+                "",
+                "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+            ),
+            (
+                "GET",
+                "/?%E1%88%B4=bar",
+                vec![],
+                "",
+                "2cdec8eed098649ff3a119c94853b13c643bcf08f8b0a1d91e12c9027818dd04",
+            ),
+            (
+                "POST",
+                "/",
+                vec![("content-type", "application/x-www-form-urlencoded")],
+                "Param1=value1",
+                "ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a",
+            ),
+        ];
+        for (method, target, extra_headers, body, expected) in cases {
+            let mut headers = HeaderMap::new();
+            let mut signed_headers = vec!["host", "x-amz-date"];
+            for (name, value) in extra_headers {
+                headers.insert(name, HeaderValue::from_static(value));
+                signed_headers.push(name);
+            }
+            signed_headers.sort();
+            headers.insert("host", HeaderValue::from_static("example.amazonaws.com"));
+            headers.insert("x-amz-date", HeaderValue::from_static("20150830T123600Z"));
+            let request = Request {
+                method: method.parse().unwrap(),
+                target: target.to_string(),
+                url: format!("http://example.amazonaws.com{}", target)
+                    .parse()
+                    .unwrap(),
+                headers,
+                body: body.as_bytes().to_vec(),
+            };
+            assert_eq!(
+                signature(
+                    &request,
+                    &signed_headers.join(";"),
+                    "20150830T123600Z",
+                    "20150830/us-east-1/service/aws4_request",
+                    "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                ),
+                expected,
+                "{} {}",
+                method,
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_pending_changes_complete() {
+        let server = MockServer::start();
+        server.pending_requests(3);
+        let client = server.client();
+        let group = group(&client, "ok-group");
+
+        let update = client
+            .zone_create(&Zone {
+                name: "ok.".to_string(),
+                email: "test@example.com".to_string(),
+                admin_group_id: group.id,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(update.status, ZoneChangeStatus::Pending);
+        match client
+            .zone_delete(&update.zone.id)
+            .unwrap_err()
+            .downcast::<ClientError>()
+        {
+            Ok(ClientError::Conflict(_)) => {}
+            other => panic!("expected conflict, got {:?}", other),
+        }
+
+        let options = WaitOptions::new().interval(Duration::from_millis(1));
+        let change = client.wait_for_zone_update(&update, &options).unwrap();
+        assert_eq!(change.status, ZoneChangeStatus::Synced);
+        let zone = client.zone(&update.zone.id).unwrap();
+        assert_eq!(zone.status, Some(ZoneStatus::Active));
+
+        server.fail_next_change("the dns backend is down");
+        let update = client
+            .record_set_create(
+                &zone.id,
+                &RecordSet::new(
+                    "www",
+                    300,
+                    vec![RecordData::A {
+                        address: "10.0.0.1".parse().unwrap(),
+                    }],
+                ),
+            )
+            .unwrap();
+        let err = client
+            .wait_for_record_set_update(&update, &options)
+            .unwrap_err();
+        match err.downcast::<ClientError>() {
+            Ok(ClientError::ChangeFailed(id, message)) => {
+                assert_eq!(id, update.id);
+                assert_eq!(message, "the dns backend is down");
+            }
+            other => panic!("expected change failed, got {:?}", other),
+        }
+        assert!(client.record_sets(&zone.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_zone_by_name() {
+        let server = MockServer::start();
+        let client = server.client();
+        let group = group(&client, "ok-group");
+        let update = client
+            .zone_create(&Zone {
+                name: "ok.".to_string(),
+                email: "test@example.com".to_string(),
+                admin_group_id: group.id,
+                ..Default::default()
+            })
+            .unwrap();
+        let id = update.zone.id;
+        assert_eq!(client.zone_by_name("OK").unwrap().id, id);
+        assert_eq!(client.zone_by_name_or_id("ok.").unwrap().id, id);
+        assert_eq!(client.zone_by_name_or_id(&id).unwrap().name, "ok.");
+        match client
+            .zone_by_name_or_id("nope.")
+            .unwrap_err()
+            .downcast::<ClientError>()
+        {
+            Ok(ClientError::UnknownZone(name)) => assert_eq!(name, "nope."),
+            other => panic!("expected unknown zone, got {:?}", other),
+        }
+
+        // a zone named like the id of another
+        client
+            .zone_create(&Zone {
+                name: id.clone(),
+                email: "test@example.com".to_string(),
+                admin_group_id: update.zone.admin_group_id,
+                ..Default::default()
+            })
+            .unwrap();
+        match client
+            .zone_by_name_or_id(&id)
+            .unwrap_err()
+            .downcast::<ClientError>()
+        {
+            Ok(ClientError::AmbiguousZone(..)) => {}
+            other => panic!("expected ambiguous zone, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_record_set_by_name_and_type() {
+        let server = MockServer::start();
+        let client = server.client();
+        let group = group(&client, "ok-group");
+        let zone = client
+            .zone_create(&Zone {
+                name: "ok.".to_string(),
+                email: "test@example.com".to_string(),
+                admin_group_id: group.id,
+                ..Default::default()
+            })
+            .unwrap()
+            .zone;
+        for (name, record) in [
+            (
+                "www",
+                RecordData::CNAME {
+                    cname: "ok.".to_string(),
+                },
+            ),
+            (
+                "www2",
+                RecordData::A {
+                    address: "10.0.0.1".parse().unwrap(),
+                },
+            ),
+            (
+                "www",
+                RecordData::TXT {
+                    text: "hi".to_string(),
+                },
+            ),
+            (
+                "@",
+                RecordData::TXT {
+                    text: "apex".to_string(),
+                },
+            ),
+        ] {
+            client
+                .record_set_create(&zone.id, &RecordSet::new(name, 300, vec![record]))
+                .unwrap();
+        }
+
+        // relative and fully qualified names find the same record set
+        for name in ["WWW", "www.OK."] {
+            let rs = client
+                .record_set_by_name_and_type(&zone, name, RecordType::CNAME)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                rs.records,
+                vec![RecordData::CNAME {
+                    cname: "ok.".to_string()
+                }]
+            );
+        }
+        for name in ["@", "ok."] {
+            let rs = client
+                .record_set_by_name_and_type(&zone, name, RecordType::TXT)
+                .unwrap()
+                .unwrap();
+            assert_eq!(rs.name, "@");
+        }
+        assert!(client
+            .record_set_by_name_and_type(&zone, "www", RecordType::A)
+            .unwrap()
+            .is_none());
+        assert!(client
+            .record_set_by_name_and_type(&zone, "www.other.", RecordType::CNAME)
+            .unwrap()
+            .is_none());
+        let names: Vec<_> = client
+            .record_sets_filtered(
+                &zone.id,
+                &ListOptions::new()
+                    .name_filter("www")
+                    .name_sort(NameSort::Desc),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|rs| rs.name)
+            .collect();
+        assert_eq!(names, vec!["www2", "www", "www"]);
+
+        for name in &["@", "ok.", "OK"] {
+            let ns = RecordSet::new(
+                name,
+                300,
+                vec![RecordData::NS {
+                    nsdname: "ns1.example.com.".to_string(),
+                }],
+            );
+            let err = client.record_set_create(&zone.id, &ns).unwrap_err();
+            match err.downcast::<ClientError>() {
+                Ok(ClientError::BadRequest(e)) => assert_eq!(e.status, 422),
+                other => panic!("expected 422, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_paging() {
+        let server = MockServer::start();
+        let client = server.client();
+        for name in &["a", "b", "c"] {
+            group(&client, name);
+        }
+        let names: Vec<_> = client
+            .groups_iter(&Default::default())
+            .max_items(2)
+            .map(|g| g.unwrap().name)
+            .collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+}
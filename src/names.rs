@@ -0,0 +1,46 @@
+//! Domain names as VinylDNS uses them.
+//!
+//! Zone names are absolute, with a trailing dot, while record set names are usually relative to
+//! their zone, with `@` for the zone's apex.
+
+/// `name` with a trailing dot
+pub fn absolute(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{}.", name)
+    }
+}
+
+/// A record set name relative to `origin` where possible, `@` for the origin itself.
+///
+/// VinylDNS record set names are usually relative already, but may also be fully qualified.
+pub fn relative_name(name: &str, origin: &str) -> String {
+    if name == "@" || name.eq_ignore_ascii_case(origin) || absolute(name) == origin {
+        return "@".to_string();
+    }
+    if !name.ends_with('.') {
+        return name.to_string();
+    }
+    let suffix = format!(".{}", origin);
+    if name.len() > suffix.len() && name.to_lowercase().ends_with(&suffix.to_lowercase()) {
+        return name[..name.len() - suffix.len()].to_string();
+    }
+    name.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relative_name() {
+        assert_eq!(absolute("ok"), "ok.");
+        assert_eq!(absolute("ok."), "ok.");
+        assert_eq!(relative_name("www", "ok."), "www");
+        assert_eq!(relative_name("www.OK.", "ok."), "www");
+        assert_eq!(relative_name("ok.", "ok."), "@");
+        assert_eq!(relative_name("@", "ok."), "@");
+        assert_eq!(relative_name("www.other.", "ok."), "www.other.");
+    }
+}
@@ -0,0 +1,401 @@
+//! Declarative management of a zone: describe the record sets it should have in a file, `plan`
+//! the changes which get it there from what it has now, and `apply` them.
+//!
+//! When the desired state names an `owner`, record sets are only changed or deleted if they are
+//! marked as belonging to it, so several tools (or people) can share a zone. The marker for a
+//! record set is a TXT record at `_owner.<name>` (`_owner` at the apex) reading
+//! `owner=<owner> type=<type>`, which `apply` creates and deletes along with the record set.
+
+#![allow(non_local_definitions)] // failure's derive
+
+use crate::api_types::*;
+use crate::client::{Client, Transport, VinylDns, WaitOptions};
+use crate::diff::{self, Diff};
+use crate::names;
+use crate::zone_file;
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+const MARKER: &str = "_owner";
+const MARKER_TTL: i32 = 300;
+
+/// The record sets a zone should have
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DesiredState {
+    /// name of the zone
+    pub zone: String,
+    /// only manage record sets marked as owned by this, or every record set in the zone if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// TTL of record sets which don't give one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i32>,
+    pub record_sets: Vec<RecordSet>,
+}
+
+#[derive(Debug, Fail)]
+pub enum PlanError {
+    #[fail(
+        display = "{} is not a desired state file, expected .yaml, .json or .toml",
+        _0
+    )]
+    UnknownFormat(String),
+    #[fail(display = "record set {} {} is listed more than once", _0, _1)]
+    Duplicate(String, RecordType),
+    #[fail(display = "record set {} {} has no TTL", _0, _1)]
+    MissingTtl(String, RecordType),
+    #[fail(display = "record set {} {} exists but is not owned by {}", _0, _1, _2)]
+    NotOwned(String, RecordType, String),
+    #[fail(display = "record set {} {} is managed by VinylDNS", _0, _1)]
+    Unmanageable(String, RecordType),
+}
+
+impl DesiredState {
+    /// Read a desired state file, in a format picked by its extension
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let input = std::fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        let mut state: DesiredState = match extension {
+            "yaml" | "yml" => serde_yaml::from_str(&input)?,
+            "json" => serde_json::from_str(&input)?,
+            "toml" => toml::from_str(&input)?,
+            _ => return Err(PlanError::UnknownFormat(path.display().to_string()).into()),
+        };
+        if let Some(ttl) = state.ttl {
+            for rs in state.record_sets.iter_mut().filter(|rs| rs.ttl == 0) {
+                rs.ttl = ttl;
+            }
+        }
+        Ok(state)
+    }
+}
+
+/// The changes to make to a zone, as a diff of the record sets it manages
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub diff: Diff,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty()
+    }
+}
+
+/// Work out the changes which turn the `current` record sets of a zone into the desired ones
+pub fn plan(desired: &DesiredState, current: &[RecordSet]) -> Result<Plan> {
+    let origin = names::absolute(&desired.zone);
+    let relative = |rs: &RecordSet| {
+        let mut rs = rs.clone();
+        rs.name = names::relative_name(&rs.name, &origin);
+        rs
+    };
+    let key = |rs: &RecordSet| (rs.name.to_lowercase(), rs.record_set_type.clone());
+
+    let mut wanted = BTreeMap::new();
+    for rs in desired.record_sets.iter().map(relative) {
+        let (name, typ) = key(&rs);
+        if !zone_file::manageable(&name, &typ) || is_marker(&name, &typ) {
+            return Err(PlanError::Unmanageable(name, typ).into());
+        }
+        if rs.ttl <= 0 {
+            return Err(PlanError::MissingTtl(name, typ).into());
+        }
+        if wanted.insert((name.clone(), typ.clone()), rs).is_some() {
+            return Err(PlanError::Duplicate(name, typ).into());
+        }
+    }
+
+    let mut existing = vec![];
+    let mut markers = BTreeMap::new();
+    for rs in current.iter().map(relative) {
+        let (name, typ) = key(&rs);
+        if is_marker(&name, &typ) {
+            let owned_name = match name.strip_prefix(MARKER) {
+                Some("") => "@".to_string(),
+                Some(rest) => rest.trim_start_matches('.').to_string(),
+                None => continue,
+            };
+            markers.insert(owned_name, rs);
+        } else if zone_file::manageable(&name, &typ) {
+            existing.push(rs);
+        }
+    }
+    let owned = |name: &str, typ: &RecordType| match &desired.owner {
+        None => true,
+        Some(owner) => markers
+            .get(name)
+            .is_some_and(|marker| marker.records.contains(&marker_record(owner, typ))),
+    };
+
+    // only the record sets this owner manages may change, and those it wants must be among them
+    let mut managed = vec![];
+    for rs in existing {
+        let (name, typ) = key(&rs);
+        if owned(&name, &typ) {
+            managed.push(rs);
+        } else if wanted.contains_key(&(name.clone(), typ.clone())) {
+            let owner = desired.owner.clone().unwrap_or_default();
+            return Err(PlanError::NotOwned(name, typ, owner).into());
+        }
+    }
+
+    let mut target = vec![];
+    if let Some(owner) = &desired.owner {
+        let mut names: Vec<_> = wanted.keys().map(|(name, _)| name.clone()).collect();
+        names.extend(markers.keys().cloned());
+        names.sort();
+        names.dedup();
+        for name in names {
+            let current = markers.get(&name);
+            // keep the markers of other owners sharing the name
+            let mut records: Vec<_> = current
+                .map(|marker| {
+                    marker
+                        .records
+                        .iter()
+                        .filter(|r| !is_owner_record(r, owner))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+            records.extend(
+                wanted
+                    .keys()
+                    .filter(|(n, _)| *n == name)
+                    .map(|(_, typ)| marker_record(owner, typ)),
+            );
+            if records.is_empty() {
+                continue;
+            }
+            target.push(match current {
+                Some(current) => RecordSet {
+                    records,
+                    ..current.clone()
+                },
+                None if name == "@" => RecordSet::new(MARKER, MARKER_TTL, records),
+                None => RecordSet::new(&format!("{}.{}", MARKER, name), MARKER_TTL, records),
+            });
+        }
+        managed.extend(markers.into_values());
+    }
+    target.extend(wanted.into_values());
+
+    Ok(Plan {
+        diff: diff::diff(&managed, &target, &origin)?,
+    })
+}
+
+fn is_marker(name: &str, typ: &RecordType) -> bool {
+    *typ == RecordType::TXT && (name == MARKER || name.starts_with(&format!("{}.", MARKER)))
+}
+
+fn marker_record(owner: &str, typ: &RecordType) -> RecordData {
+    RecordData::TXT {
+        text: format!("owner={} type={}", owner, typ),
+    }
+}
+
+fn is_owner_record(record: &RecordData, owner: &str) -> bool {
+    match record {
+        RecordData::TXT { text } => text.starts_with(&format!("owner={} ", owner)),
+        _ => false,
+    }
+}
+
+/// Make the planned changes one record set at a time, optionally waiting for each to complete
+pub fn apply<T: Transport>(
+    client: &Client<T>,
+    zone: &Zone,
+    plan: &Plan,
+    wait: Option<&WaitOptions>,
+) -> Result<()> {
+    let wait_for = |update: RecordSetUpdateResponse| -> Result<()> {
+        if let Some(wait) = wait {
+            client.wait_for_record_set_update(&update, wait)?;
+        }
+        Ok(())
+    };
+    for rs in &plan.diff.removed {
+        wait_for(client.record_set_delete(&zone.id, &rs.id)?)?;
+    }
+    for changed in &plan.diff.changed {
+        let rs = RecordSet {
+            id: changed.old.id.clone(),
+            zone_id: changed.old.zone_id.clone(),
+            ..changed.new.clone()
+        };
+        wait_for(client.record_set_update(&zone.id, &rs.id, &rs)?)?;
+    }
+    for rs in &plan.diff.added {
+        wait_for(client.record_set_create(&zone.id, rs)?)?;
+    }
+    Ok(())
+}
+
+/// Make the planned changes as a single batch change, which is applied atomically
+pub fn apply_batch<T: Transport>(
+    client: &Client<T>,
+    zone: &Zone,
+    plan: &Plan,
+) -> Result<BatchRecordChange> {
+    let origin = names::absolute(&zone.name);
+    let fqdn = |rs: &RecordSet| match names::relative_name(&rs.name, &origin).as_str() {
+        "@" => origin.clone(),
+        name => format!("{}.{}", name, origin),
+    };
+    let delete = |rs: &RecordSet| RecordChangeInput {
+        input_name: fqdn(rs),
+        change_type: ChangeInputType::DeleteRecordSet,
+        typ: rs.record_set_type.clone(),
+        ..Default::default()
+    };
+    let add = |rs: &RecordSet| {
+        rs.records
+            .iter()
+            .map(|record| RecordChangeInput {
+                input_name: fqdn(rs),
+                change_type: ChangeInputType::Add,
+                typ: rs.record_set_type.clone(),
+                ttl: Some(rs.ttl),
+                record: Some(record.clone().into()),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut changes: Vec<_> = plan.diff.removed.iter().map(delete).collect();
+    for changed in &plan.diff.changed {
+        changes.push(delete(&changed.old));
+        changes.extend(add(&changed.new));
+    }
+    changes.extend(plan.diff.added.iter().flat_map(add));
+    client.batch_change_create(
+        &BatchRecordChangeInput {
+            comments: Some("applied from desired state".to_string()),
+            owner_group_id: None,
+            changes,
+        },
+        false,
+    )
+}
+
+/// The diff, deletes first so that names are free for the creates, then a count of each
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        write!(
+            f,
+            "{}{} to create, {} to update, {} to delete",
+            self.diff,
+            self.diff.added.len(),
+            self.diff.changed.len(),
+            self.diff.removed.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn a(name: &str, ttl: i32, addresses: &[&str]) -> RecordSet {
+        RecordSet::new(
+            name,
+            ttl,
+            addresses
+                .iter()
+                .map(|a| RecordData::A {
+                    address: a.parse().unwrap(),
+                })
+                .collect(),
+        )
+    }
+
+    fn existing(mut rs: RecordSet, id: &str) -> RecordSet {
+        rs.id = id.to_string();
+        rs
+    }
+
+    #[test]
+    fn test_plan() {
+        let desired = DesiredState {
+            zone: "ok.".to_string(),
+            record_sets: vec![
+                a("www", 300, &["10.0.0.1", "10.0.0.2"]),
+                a("new", 300, &["10.0.0.3"]),
+                a("ttl", 600, &["10.0.0.4"]),
+            ],
+            ..Default::default()
+        };
+        let current = vec![
+            existing(a("www.ok.", 300, &["10.0.0.2", "10.0.0.1"]), "1"),
+            existing(a("ttl", 300, &["10.0.0.4"]), "2"),
+            existing(a("old", 300, &["10.0.0.5"]), "3"),
+        ];
+        let plan = plan(&desired, &current).unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "- old A\n    - 10.0.0.5\n~ ttl A\n    ttl 300 -> 600\n+ new A ttl 300\n    + 10.0.0.3\n1 to create, 1 to update, 1 to delete"
+        );
+        assert_eq!(plan.diff.changed[0].old.id, "2");
+    }
+
+    #[test]
+    fn test_plan_owner() {
+        let desired = DesiredState {
+            zone: "ok.".to_string(),
+            owner: Some("me".to_string()),
+            record_sets: vec![a("www", 300, &["10.0.0.1"])],
+            ..Default::default()
+        };
+        let marker = |name: &str, texts: &[&str]| {
+            RecordSet::new(
+                name,
+                300,
+                texts
+                    .iter()
+                    .map(|t| RecordData::TXT {
+                        text: t.to_string(),
+                    })
+                    .collect(),
+            )
+        };
+
+        // records without a marker are left alone, or refused if they're wanted
+        let current = vec![existing(a("other", 300, &["10.0.0.9"]), "1")];
+        let p = plan(&desired, &current).unwrap();
+        assert_eq!(
+            p.to_string(),
+            "+ _owner.www TXT ttl 300\n    + \"owner=me type=A\"\n+ www A ttl 300\n    + 10.0.0.1\n2 to create, 0 to update, 0 to delete"
+        );
+        let current = vec![existing(a("www", 300, &["10.0.0.9"]), "1")];
+        assert!(plan(&desired, &current).is_err());
+
+        // owned records are updated and deleted, along with their markers
+        let current = vec![
+            existing(a("www", 300, &["10.0.0.1"]), "1"),
+            existing(marker("_owner.www", &["owner=me type=A"]), "2"),
+            existing(a("old", 300, &["10.0.0.2"]), "3"),
+            existing(
+                marker("_owner.old", &["owner=me type=A", "owner=you type=AAAA"]),
+                "4",
+            ),
+        ];
+        let p = plan(&desired, &current).unwrap();
+        assert_eq!(
+            p.to_string(),
+            "- old A\n    - 10.0.0.2\n~ _owner.old TXT\n    - \"owner=me type=A\"\n0 to create, 1 to update, 1 to delete"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! How a `Client` gets a signed request to the server and a response back.
+//!
+//! `reqwest::blocking::Client` is the default transport, and `reqwest::Client` the default
+//! `AsyncTransport` of an `AsyncClient`. `MemoryTransport` is both, answering requests from
+//! canned responses instead, for testing code built on either client without a server.
+
+use crate::api::SignedRequest;
+use crate::client::ClientError;
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+/// Sends a signed request, failing only if no response was received.
+///
+/// Non-2xx responses are not errors at this level, so wrappers adding logging or recording see
+/// every response.
+pub trait Transport {
+    fn send(&self, request: SignedRequest) -> Result<Response>;
+}
+
+/// The async counterpart of `Transport`, sending the requests of an `AsyncClient`
+pub trait AsyncTransport: Send + Sync {
+    fn send(&self, request: SignedRequest) -> ResponseFuture<'_>;
+}
+
+/// A response an `AsyncTransport` is waiting for
+pub type ResponseFuture<'a> = Pin<Box<dyn Future<Output = Result<Response>> + Send + 'a>>;
+
+/// A raw response from the server
+#[derive(Debug, Clone, Default)]
+pub struct Response {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, body: &str) -> Self {
+        Response {
+            status,
+            headers: HeaderMap::new(),
+            body: body.to_string(),
+        }
+    }
+}
+
+impl Transport for reqwest::blocking::Client {
+    fn send(&self, request: SignedRequest) -> Result<Response> {
+        let mut req = reqwest::blocking::Request::new(request.method, request.url);
+        *req.headers_mut() = request.headers;
+        *req.body_mut() = Some(request.body.into());
+
+        let res = self
+            .execute(req)
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+        let status = res.status().as_u16();
+        let headers = res.headers().clone();
+        let body = res
+            .text()
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl AsyncTransport for reqwest::Client {
+    fn send(&self, request: SignedRequest) -> ResponseFuture<'_> {
+        let mut req = reqwest::Request::new(request.method, request.url);
+        *req.headers_mut() = request.headers;
+        *req.body_mut() = Some(request.body.into());
+
+        Box::pin(async move {
+            let res = self
+                .execute(req)
+                .await
+                .map_err(|e| ClientError::Transport(e.to_string()))?;
+            let status = res.status().as_u16();
+            let headers = res.headers().clone();
+            let body = res
+                .text()
+                .await
+                .map_err(|e| ClientError::Transport(e.to_string()))?;
+            Ok(Response {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, request: SignedRequest) -> Result<Response> {
+        (**self).send(request)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn send(&self, request: SignedRequest) -> Result<Response> {
+        (**self).send(request)
+    }
+}
+
+impl<T: AsyncTransport + ?Sized> AsyncTransport for Box<T> {
+    fn send(&self, request: SignedRequest) -> ResponseFuture<'_> {
+        (**self).send(request)
+    }
+}
+
+impl<T: AsyncTransport + ?Sized> AsyncTransport for &T {
+    fn send(&self, request: SignedRequest) -> ResponseFuture<'_> {
+        (**self).send(request)
+    }
+}
+
+/// Answers requests with responses registered by method and path, recording every request.
+///
+/// Responses registered for the same route are returned in order, with the last one repeated
+/// once the others have been used up, so a 503 followed by a 200 tests a retry. Requests with
+/// no registered route get a 404.
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    routes: Mutex<Vec<(Method, String, VecDeque<Response>)>>,
+    requests: Mutex<Vec<SignedRequest>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Respond to `method` requests for `path` (ignoring the query string) with `status` and
+    /// `body`
+    pub fn respond(self, method: Method, path: &str, status: u16, body: &str) -> Self {
+        self.respond_with(method, path, Response::new(status, body))
+    }
+
+    pub fn respond_with(self, method: Method, path: &str, response: Response) -> Self {
+        {
+            let mut routes = self.routes.lock().unwrap();
+            match routes
+                .iter_mut()
+                .find(|(m, p, _)| *m == method && p == path)
+            {
+                Some((_, _, responses)) => responses.push_back(response),
+                None => routes.push((method, path.to_string(), vec![response].into())),
+            }
+        }
+        self
+    }
+
+    /// Every request sent so far, oldest first
+    pub fn requests(&self) -> Vec<SignedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&self, request: SignedRequest) -> Result<Response> {
+        let response = {
+            let mut routes = self.routes.lock().unwrap();
+            let route = routes
+                .iter_mut()
+                .find(|(m, p, _)| *m == request.method && p == request.url.path());
+            match route {
+                Some((_, _, responses)) if responses.len() > 1 => responses.pop_front().unwrap(),
+                Some((_, _, responses)) => responses[0].clone(),
+                None => Response::new(404, "The requested path was not found"),
+            }
+        };
+        self.requests.lock().unwrap().push(request);
+        Ok(response)
+    }
+}
+
+impl AsyncTransport for MemoryTransport {
+    fn send(&self, request: SignedRequest) -> ResponseFuture<'_> {
+        Box::pin(std::future::ready(Transport::send(self, request)))
+    }
+}
@@ -0,0 +1,905 @@
+//! Zone files in the RFC 1035 master file format, as used by BIND and most other DNS servers
+
+#![allow(non_local_definitions)] // failure's derive
+
+use crate::api_types::*;
+use crate::client::{Client, Transport, VinylDns};
+use crate::names::{absolute, relative_name};
+use failure::Fail;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+
+type Result<R> = core::result::Result<R, failure::Error>;
+
+/// Render every record set of a zone as a master file
+pub fn export_zone<T: Transport>(client: &Client<T>, zone_id: &str) -> Result<String> {
+    let zone = client.zone(zone_id)?;
+    let record_sets = client.record_sets(zone_id)?;
+    Ok(export(&zone, &record_sets))
+}
+
+/// Render record sets of `zone` as a master file.
+///
+/// `$TTL` is set to the most common TTL, so only record sets with a different one list theirs.
+/// Records of types this crate doesn't know the data of are left in as comments.
+pub fn export(zone: &Zone, record_sets: &[RecordSet]) -> String {
+    let origin = absolute(&zone.name);
+    let default_ttl = most_common_ttl(record_sets);
+
+    let mut record_sets: Vec<_> = record_sets.iter().collect();
+    // SOA and then the apex first, like a hand written zone file
+    record_sets.sort_by_key(|rs| {
+        let name = relative_name(&rs.name, &origin);
+        (
+            rs.record_set_type != RecordType::SOA,
+            name != "@",
+            name,
+            rs.record_set_type.to_string(),
+        )
+    });
+
+    let mut out = String::new();
+    writeln!(out, "$ORIGIN {}", origin).unwrap();
+    writeln!(out, "$TTL {}", default_ttl).unwrap();
+    for rs in record_sets {
+        let name = relative_name(&rs.name, &origin);
+        let ttl = if rs.ttl == default_ttl {
+            String::new()
+        } else {
+            rs.ttl.to_string()
+        };
+        for record in &rs.records {
+            let line = format!(
+                "{:<23} {:<7} IN {:<5} ",
+                name,
+                ttl,
+                rs.record_set_type.as_str()
+            );
+            match rdata(record) {
+                Some(rdata) => writeln!(out, "{}{}", line, rdata).unwrap(),
+                None => writeln!(out, "; {}(unsupported record data)", line).unwrap(),
+            }
+        }
+    }
+    out
+}
+
+fn most_common_ttl(record_sets: &[RecordSet]) -> i32 {
+    let mut counts = HashMap::new();
+    for rs in record_sets {
+        *counts.entry(rs.ttl).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        // on a tie, the lowest ttl
+        .max_by_key(|&(ttl, count)| (count, -ttl))
+        .map(|(ttl, _)| ttl)
+        .unwrap_or(3600)
+}
+
+/// Character strings are quoted, and split into the 255 byte chunks DNS allows
+fn quote(text: &str) -> String {
+    let mut chunks = vec![];
+    let mut chunk = String::new();
+    for c in text.chars() {
+        if chunk.len() + c.len_utf8() > 255 {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        chunk.push(c);
+    }
+    chunks.push(chunk);
+    chunks
+        .iter()
+        .map(|chunk| format!("\"{}\"", chunk.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn rdata(record: &RecordData) -> Option<String> {
+    use RecordData::*;
+    Some(match record {
+        A { address } => address.to_string(),
+        AAAA { address } => address.to_string(),
+        CNAME { cname } => cname.clone(),
+        DS {
+            keytag,
+            algorithm,
+            digesttype,
+            digest,
+        } => format!("{} {} {} {}", keytag, algorithm, digesttype, digest),
+        MX {
+            preference,
+            exchange,
+        } => format!("{} {}", preference, exchange),
+        NAPTR {
+            order,
+            preference,
+            flags,
+            service,
+            regexp,
+            replacement,
+        } => format!(
+            "{} {} {} {} {} {}",
+            order,
+            preference,
+            quote(flags),
+            quote(service),
+            quote(regexp),
+            replacement
+        ),
+        NS { nsdname } => nsdname.clone(),
+        PTR { ptrdname } => ptrdname.clone(),
+        SOA {
+            mname,
+            rname,
+            serial,
+            refresh,
+            retry,
+            expire,
+            minimum,
+        } => format!(
+            "{} {} {} {} {} {} {}",
+            mname, rname, serial, refresh, retry, expire, minimum
+        ),
+        SPF { text } | TXT { text } => quote(text),
+        SRV {
+            priority,
+            weight,
+            port,
+            target,
+        } => format!("{} {} {} {}", priority, weight, port, target),
+        SSHFP {
+            algorithm,
+            typ,
+            fingerprint,
+        } => format!("{} {} {}", algorithm, typ, fingerprint),
+        Unknown(..) => return None,
+    })
+}
+
+/// Create record sets parsed from a zone file in an existing zone.
+///
+/// Record sets which aren't `importable` are skipped. Stops at the first record set the server
+/// rejects, e.g. because it already exists, returning an `ImportError` with the changes made
+/// before it.
+pub fn import_zone<T: Transport>(
+    client: &Client<T>,
+    zone: &Zone,
+    record_sets: &[RecordSet],
+) -> Result<Vec<RecordSetUpdateResponse>> {
+    let mut created = vec![];
+    for rs in record_sets.iter().filter(|rs| importable(rs, &zone.name)) {
+        match client.record_set_create(&zone.id, rs) {
+            Ok(change) => created.push(change),
+            Err(error) => {
+                return Err(ImportError {
+                    created,
+                    failed: rs.clone(),
+                    error,
+                }
+                .into())
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// VinylDNS maintains the SOA and apex NS records of a zone itself, so they can't be imported
+pub fn importable(rs: &RecordSet, origin: &str) -> bool {
+    manageable(
+        &relative_name(&rs.name, &absolute(origin)),
+        &rs.record_set_type,
+    )
+}
+
+/// Whether a record set, named relative to its zone, can be changed through the api
+pub(crate) fn manageable(name: &str, typ: &RecordType) -> bool {
+    match typ {
+        RecordType::SOA => false,
+        RecordType::NS => name != "@",
+        _ => true,
+    }
+}
+
+/// A zone import which failed part way through
+#[derive(Debug)]
+pub struct ImportError {
+    /// changes of the record sets created before the failure
+    pub created: Vec<RecordSetUpdateResponse>,
+    /// the record set which couldn't be created
+    pub failed: RecordSet,
+    pub error: failure::Error,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to import {} {} after creating {} record sets",
+            self.failed.name,
+            self.failed.record_set_type,
+            self.created.len()
+        )?;
+        let created: Vec<_> = self
+            .created
+            .iter()
+            .map(|c| format!("{} {}", c.record_set.name, c.record_set.record_set_type))
+            .collect();
+        if !created.is_empty() {
+            write!(f, " ({})", created.join(", "))?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl Fail for ImportError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        Some(self.error.as_fail())
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum ZoneFileError {
+    #[fail(display = "{}:{}: {}", _0, _1, _2)]
+    Syntax(String, usize, String),
+    #[fail(display = "failed to read {}: {}", _0, _1)]
+    Read(String, std::io::Error),
+    #[fail(display = "{}:{}: $INCLUDE of {} includes itself", _0, _1, _2)]
+    RecursiveInclude(String, usize, String),
+}
+
+/// Parse a master file into record sets named relative to `origin`, the zone's name.
+///
+/// Files named by `$INCLUDE` are read relative to the working directory.
+#[cfg(test)]
+pub fn parse(input: &str, origin: &str) -> Result<Vec<RecordSet>> {
+    let mut parser = Parser::new(origin, PathBuf::new());
+    parser.parse(input, "<input>")?;
+    parser.record_sets()
+}
+
+/// Parse a master file like `parse`, with `$INCLUDE`d files read relative to its directory
+pub fn parse_file(path: &Path, origin: &str) -> Result<Vec<RecordSet>> {
+    let input = std::fs::read_to_string(path)
+        .map_err(|e| ZoneFileError::Read(path.display().to_string(), e))?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut parser = Parser::new(origin, dir);
+    parser
+        .including
+        .push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+    parser.parse(&input, &path.display().to_string())?;
+    parser.record_sets()
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+/// The tokens of one entry, which may span several lines when parenthesized
+#[derive(Debug)]
+struct Entry {
+    line: usize,
+    tokens: Vec<Token>,
+    /// the entry started with whitespace, so it belongs to the previous owner
+    blank_owner: bool,
+}
+
+fn tokenize(input: &str, file: &str) -> core::result::Result<Vec<Entry>, ZoneFileError> {
+    let syntax =
+        |line, message: &str| ZoneFileError::Syntax(file.to_string(), line, message.into());
+    let mut entries = vec![];
+    let mut entry = Entry {
+        line: 1,
+        tokens: vec![],
+        blank_owner: false,
+    };
+    let mut line = 1;
+    let mut depth = 0;
+    let mut line_start = true;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let was_line_start = std::mem::replace(&mut line_start, c == '\n');
+        match c {
+            '\n' => {
+                line += 1;
+                if depth == 0 {
+                    let next = Entry {
+                        line,
+                        tokens: vec![],
+                        blank_owner: false,
+                    };
+                    let done = std::mem::replace(&mut entry, next);
+                    if !done.tokens.is_empty() {
+                        entries.push(done);
+                    }
+                }
+            }
+            ';' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return Err(syntax(line, "unbalanced ')'"));
+                }
+                depth -= 1;
+            }
+            ' ' | '\t' | '\r' => {
+                if was_line_start && depth == 0 {
+                    entry.blank_owner = true;
+                }
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.push(unescape(&mut chars)),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            text.push(c)
+                        }
+                        None => return Err(syntax(line, "unterminated quoted string")),
+                    }
+                }
+                if entry.tokens.is_empty() {
+                    entry.line = line;
+                }
+                entry.tokens.push(Token { text, quoted: true });
+            }
+            c => {
+                let mut text = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "();\"".contains(c) {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                if entry.tokens.is_empty() {
+                    entry.line = line;
+                }
+                entry.tokens.push(Token {
+                    text,
+                    quoted: false,
+                });
+            }
+        }
+    }
+    if depth != 0 {
+        return Err(syntax(line, "unbalanced '('"));
+    }
+    if !entry.tokens.is_empty() {
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// The character after a `\` in a quoted string, either itself or a `\DDD` decimal escape
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> char {
+    let mut digits = String::new();
+    while digits.len() < 3 && chars.peek().is_some_and(char::is_ascii_digit) {
+        digits.push(chars.next().unwrap());
+    }
+    if digits.is_empty() {
+        return chars.next().unwrap_or('\\');
+    }
+    digits
+        .parse::<u8>()
+        .map(char::from)
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// A TTL in seconds, or with BIND's unit suffixes like `1h30m`
+fn parse_ttl(s: &str) -> Option<i32> {
+    if let Ok(ttl) = s.parse() {
+        return Some(ttl);
+    }
+    let mut total: i32 = 0;
+    let mut n = String::new();
+    for c in s.to_lowercase().chars() {
+        if c.is_ascii_digit() {
+            n.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        total = n
+            .parse::<i32>()
+            .ok()?
+            .checked_mul(unit)
+            .and_then(|n| total.checked_add(n))?;
+        n.clear();
+    }
+    if !n.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+struct Parser {
+    zone: String,
+    origin: String,
+    dir: PathBuf,
+    /// the files being parsed, to catch files including themselves
+    including: Vec<PathBuf>,
+    default_ttl: Option<i32>,
+    last_ttl: Option<i32>,
+    last_owner: Option<String>,
+    records: Vec<(String, RecordType, i32, RecordData, String, usize)>,
+}
+
+impl Parser {
+    fn new(origin: &str, dir: PathBuf) -> Self {
+        Parser {
+            zone: absolute(origin).to_lowercase(),
+            origin: absolute(origin),
+            dir,
+            including: vec![],
+            default_ttl: None,
+            last_ttl: None,
+            last_owner: None,
+            records: vec![],
+        }
+    }
+
+    fn parse(&mut self, input: &str, file: &str) -> Result<()> {
+        for entry in tokenize(input, file)? {
+            let syntax =
+                |message: String| ZoneFileError::Syntax(file.to_string(), entry.line, message);
+            let first = &entry.tokens[0];
+            if !entry.blank_owner && !first.quoted && first.text.starts_with('$') {
+                let arg = |i: usize| {
+                    entry
+                        .tokens
+                        .get(i)
+                        .map(|t| t.text.as_str())
+                        .ok_or_else(|| syntax(format!("{} needs an argument", first.text)))
+                };
+                match first.text.to_uppercase().as_str() {
+                    "$ORIGIN" => self.origin = self.name(arg(1)?),
+                    "$TTL" => {
+                        let ttl = arg(1)?;
+                        self.default_ttl = Some(
+                            parse_ttl(ttl).ok_or_else(|| syntax(format!("invalid TTL {}", ttl)))?,
+                        );
+                    }
+                    "$INCLUDE" => {
+                        let path = self.dir.join(arg(1)?);
+                        let input = std::fs::read_to_string(&path)
+                            .map_err(|e| ZoneFileError::Read(path.display().to_string(), e))?;
+                        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+                        if self.including.contains(&canonical) {
+                            return Err(ZoneFileError::RecursiveInclude(
+                                file.to_string(),
+                                entry.line,
+                                path.display().to_string(),
+                            )
+                            .into());
+                        }
+                        // the included file may set its own origin, but it doesn't leak out
+                        let origin = self.origin.clone();
+                        if let Ok(include_origin) = arg(2) {
+                            self.origin = self.name(include_origin);
+                        }
+                        self.including.push(canonical);
+                        self.parse(&input, &path.display().to_string())?;
+                        self.including.pop();
+                        self.origin = origin;
+                    }
+                    other => return Err(syntax(format!("unknown directive {}", other)).into()),
+                }
+                continue;
+            }
+
+            let mut tokens = entry.tokens.iter();
+            let owner = if entry.blank_owner {
+                self.last_owner
+                    .clone()
+                    .ok_or_else(|| syntax("record has no owner name".to_string()))?
+            } else {
+                self.name(&tokens.next().unwrap().text)
+            };
+            self.last_owner = Some(owner.clone());
+
+            let mut ttl = None;
+            let typ = loop {
+                let token = tokens
+                    .next()
+                    .ok_or_else(|| syntax("record has no type".to_string()))?;
+                let text = token.text.to_uppercase();
+                if text.starts_with(|c: char| c.is_ascii_digit()) {
+                    ttl = Some(
+                        parse_ttl(&text).ok_or_else(|| syntax(format!("invalid TTL {}", text)))?,
+                    );
+                } else if text == "IN" {
+                } else if ["CH", "HS", "CS"].contains(&text.as_str()) {
+                    return Err(syntax(format!("unsupported class {}", text)).into());
+                } else {
+                    match text.parse().unwrap() {
+                        RecordType::Unknown(_) => {
+                            return Err(syntax(format!("unsupported record type {}", text)).into())
+                        }
+                        typ => break typ,
+                    }
+                }
+            };
+            let rdata: Vec<_> = tokens.cloned().collect();
+            let data = self
+                .rdata(&typ, &rdata)
+                .map_err(|message| syntax(format!("{} record {}", typ, message)))?;
+
+            let ttl = ttl
+                .or(self.default_ttl)
+                .or(self.last_ttl)
+                .ok_or_else(|| syntax("record has no TTL and no $TTL is set".to_string()))?;
+            self.last_ttl = Some(ttl);
+            self.records
+                .push((owner, typ, ttl, data, file.to_string(), entry.line));
+        }
+        Ok(())
+    }
+
+    /// A name from the file made absolute
+    fn name(&self, name: &str) -> String {
+        if name == "@" {
+            self.origin.clone()
+        } else if name.ends_with('.') {
+            name.to_string()
+        } else {
+            format!("{}.{}", name, self.origin)
+        }
+    }
+
+    fn rdata(
+        &self,
+        typ: &RecordType,
+        tokens: &[Token],
+    ) -> core::result::Result<RecordData, String> {
+        let text = |i: usize| {
+            tokens
+                .get(i)
+                .map(|t| t.text.clone())
+                .ok_or_else(|| format!("is missing data, expected at least {} fields", i + 1))
+        };
+        fn num<N: std::str::FromStr>(s: String) -> core::result::Result<N, String> {
+            s.parse().map_err(|_| format!("has invalid number {}", s))
+        }
+        let name = |i: usize| text(i).map(|n| self.name(&n));
+        let strings = |from: usize| {
+            if tokens.len() <= from {
+                return Err("is missing its text".to_string());
+            }
+            Ok(tokens[from..]
+                .iter()
+                .map(|t| t.text.as_str())
+                .collect::<String>())
+        };
+
+        use RecordData::*;
+        Ok(match typ {
+            RecordType::A => A {
+                address: text(0)?.parse().map_err(|_| "has an invalid address")?,
+            },
+            RecordType::AAAA => AAAA {
+                address: text(0)?.parse().map_err(|_| "has an invalid address")?,
+            },
+            RecordType::CNAME => CNAME { cname: name(0)? },
+            RecordType::DS => DS {
+                keytag: num(text(0)?)?,
+                algorithm: num(text(1)?)?,
+                digesttype: num(text(2)?)?,
+                // long digests are often split over several fields
+                digest: tokens
+                    .get(3..)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| t.text.as_str())
+                    .collect(),
+            },
+            RecordType::MX => MX {
+                preference: num(text(0)?)?,
+                exchange: name(1)?,
+            },
+            RecordType::NAPTR => NAPTR {
+                order: num(text(0)?)?,
+                preference: num(text(1)?)?,
+                flags: text(2)?,
+                service: text(3)?,
+                regexp: text(4)?,
+                replacement: name(5)?,
+            },
+            RecordType::NS => NS { nsdname: name(0)? },
+            RecordType::PTR => PTR { ptrdname: name(0)? },
+            RecordType::SOA => SOA {
+                mname: name(0)?,
+                rname: name(1)?,
+                serial: num(text(2)?)?,
+                refresh: parse_ttl(&text(3)?).ok_or("has an invalid refresh")?.into(),
+                retry: parse_ttl(&text(4)?).ok_or("has an invalid retry")?.into(),
+                expire: parse_ttl(&text(5)?).ok_or("has an invalid expire")?.into(),
+                minimum: parse_ttl(&text(6)?).ok_or("has an invalid minimum")?.into(),
+            },
+            RecordType::SPF => SPF { text: strings(0)? },
+            RecordType::SRV => SRV {
+                priority: num(text(0)?)?,
+                weight: num(text(1)?)?,
+                port: num(text(2)?)?,
+                target: name(3)?,
+            },
+            RecordType::SSHFP => SSHFP {
+                algorithm: num(text(0)?)?,
+                typ: num(text(1)?)?,
+                fingerprint: tokens
+                    .get(2..)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| t.text.as_str())
+                    .collect(),
+            },
+            RecordType::TXT => TXT { text: strings(0)? },
+            RecordType::Unknown(_) => unreachable!("unknown types are rejected before their data"),
+        })
+    }
+
+    /// Group the parsed records into record sets by owner and type, in the order they appeared
+    fn record_sets(self) -> Result<Vec<RecordSet>> {
+        let zone = self.zone;
+        let mut record_sets: Vec<RecordSet> = vec![];
+        for (owner, typ, ttl, data, file, line) in self.records {
+            let lower = owner.to_lowercase();
+            let name = if lower == zone {
+                "@".to_string()
+            } else if lower.ends_with(&format!(".{}", zone)) {
+                owner[..owner.len() - zone.len() - 1].to_string()
+            } else {
+                return Err(ZoneFileError::Syntax(
+                    file,
+                    line,
+                    format!("{} is not in zone {}", owner, zone),
+                )
+                .into());
+            };
+            match record_sets
+                .iter_mut()
+                .find(|rs| rs.record_set_type == typ && rs.name.eq_ignore_ascii_case(&name))
+            {
+                Some(rs) => rs.records.push(data),
+                None => record_sets.push(RecordSet::new(&name, ttl, vec![data])),
+            }
+        }
+        Ok(record_sets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let zone = Zone {
+            name: "ok.".to_string(),
+            ..Default::default()
+        };
+        let record_sets = vec![
+            RecordSet::new(
+                "www",
+                300,
+                vec![
+                    RecordData::A {
+                        address: "10.0.0.1".parse().unwrap(),
+                    },
+                    RecordData::A {
+                        address: "10.0.0.2".parse().unwrap(),
+                    },
+                ],
+            ),
+            RecordSet::new(
+                "mail.ok.",
+                300,
+                vec![RecordData::MX {
+                    preference: 10,
+                    exchange: "mx.example.com.".to_string(),
+                }],
+            ),
+            RecordSet::new(
+                "ok.",
+                86400,
+                vec![RecordData::TXT {
+                    text: r#"say "hi""#.to_string(),
+                }],
+            ),
+            RecordSet::new(
+                "@",
+                3600,
+                vec![RecordData::SOA {
+                    mname: "ns1.ok.".to_string(),
+                    rname: "admin.ok.".to_string(),
+                    serial: 1,
+                    refresh: 7200,
+                    retry: 3600,
+                    expire: 604800,
+                    minimum: 300,
+                }],
+            ),
+        ];
+        let expected = r#"$ORIGIN ok.
+$TTL 300
+@                       3600    IN SOA   ns1.ok. admin.ok. 1 7200 3600 604800 300
+@                       86400   IN TXT   "say \"hi\""
+mail                            IN MX    10 mx.example.com.
+www                             IN A     10.0.0.1
+www                             IN A     10.0.0.2
+"#;
+        assert_eq!(export(&zone, &record_sets), expected);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r"a\b"), r#""a\\b""#);
+        let long = "x".repeat(300);
+        assert_eq!(
+            quote(&long),
+            format!("\"{}\" \"{}\"", "x".repeat(255), "x".repeat(45))
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let input = r#"
+$ORIGIN ok.
+$TTL 1h
+@       IN SOA ns1 admin.ok. (
+                2019022501 ; serial
+                2h 1h 1w 300 )
+        IN NS  ns1.example.com.
+www     300 IN A 10.0.0.1
+        300    A 10.0.0.2   ; same owner
+mail.ok. IN 600 MX 10 mx
+txt     TXT "v=spf1 \"quoted\" " "and more\059"
+$ORIGIN sub.ok.
+srv     SRV 0 5 5060 sip
+"#;
+        let record_sets = parse(input, "ok").unwrap();
+        let summary: Vec<_> = record_sets
+            .iter()
+            .map(|rs| (rs.name.as_str(), rs.record_set_type.as_str(), rs.ttl))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("@", "SOA", 3600),
+                ("@", "NS", 3600),
+                ("www", "A", 300),
+                ("mail", "MX", 600),
+                ("txt", "TXT", 3600),
+                ("srv.sub", "SRV", 3600),
+            ]
+        );
+        assert_eq!(record_sets[2].records.len(), 2);
+        assert_eq!(
+            record_sets[0].records[0],
+            RecordData::SOA {
+                mname: "ns1.ok.".to_string(),
+                rname: "admin.ok.".to_string(),
+                serial: 2019022501,
+                refresh: 7200,
+                retry: 3600,
+                expire: 604800,
+                minimum: 300,
+            }
+        );
+        assert_eq!(
+            record_sets[3].records[0],
+            RecordData::MX {
+                preference: 10,
+                exchange: "mx.ok.".to_string(),
+            }
+        );
+        assert_eq!(
+            record_sets[4].records[0],
+            RecordData::TXT {
+                text: r#"v=spf1 "quoted" and more;"#.to_string(),
+            }
+        );
+        assert_eq!(
+            record_sets[5].records[0],
+            RecordData::SRV {
+                priority: 0,
+                weight: 5,
+                port: 5060,
+                target: "sip.sub.ok.".to_string(),
+            }
+        );
+
+        // and back again
+        let zone = Zone {
+            name: "ok.".to_string(),
+            ..Default::default()
+        };
+        let exported = export(&zone, &record_sets);
+        let mut reparsed = parse(&exported, "ok.").unwrap();
+        reparsed.sort_by_key(|rs| (rs.name.clone(), rs.record_set_type.to_string()));
+        let mut original = record_sets;
+        original.sort_by_key(|rs| (rs.name.clone(), rs.record_set_type.to_string()));
+        for (a, b) in original.iter().zip(&reparsed) {
+            assert_eq!((&a.name, a.ttl, &a.records), (&b.name, b.ttl, &b.records));
+        }
+    }
+
+    #[test]
+    fn test_parse_include() {
+        let dir = std::env::temp_dir().join(format!("vinyldns-zone-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("db.ok"),
+            "$TTL 300\n$INCLUDE hosts.ok hosts\nwww CNAME host1.hosts\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("hosts.ok"), "host1 A 10.0.0.1\n").unwrap();
+        std::fs::write(dir.join("loop.ok"), "$TTL 300\n$INCLUDE loop.ok\n").unwrap();
+
+        let record_sets = parse_file(&dir.join("db.ok"), "ok.").unwrap();
+        let looping = dir.join("loop.ok").display().to_string();
+        assert_eq!(
+            parse_file(&dir.join("loop.ok"), "ok.")
+                .unwrap_err()
+                .to_string(),
+            format!("{}:2: $INCLUDE of {} includes itself", looping, looping)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<_> = record_sets.iter().map(|rs| rs.name.as_str()).collect();
+        assert_eq!(names, vec!["host1.hosts", "www"]);
+        assert_eq!(
+            record_sets[1].records[0],
+            RecordData::CNAME {
+                cname: "host1.hosts.ok.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |input: &str| parse(input, "ok.").unwrap_err().to_string();
+        assert_eq!(
+            err("www A 10.0.0.1\n"),
+            "<input>:1: record has no TTL and no $TTL is set"
+        );
+        assert_eq!(
+            err("$TTL 300\n\nwww A 10.0.0\n"),
+            "<input>:3: A record has an invalid address"
+        );
+        assert_eq!(
+            err("$TTL 300\nwww.example.com. A 10.0.0.1\n"),
+            "<input>:2: www.example.com. is not in zone ok."
+        );
+        assert_eq!(
+            err("$TTL 300\nwww CAA 0 issue ca\n"),
+            "<input>:2: unsupported record type CAA"
+        );
+        assert_eq!(
+            err("$TTL 300\nwww TXT (\"a\"\n"),
+            "<input>:3: unbalanced '('"
+        );
+        assert_eq!(err("$TTL 4000w\n"), "<input>:1: invalid TTL 4000w");
+        assert_eq!(
+            err("$TTL 300\nwww 3000000000 A 10.0.0.1\n"),
+            "<input>:2: invalid TTL 3000000000"
+        );
+    }
+}